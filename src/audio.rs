@@ -0,0 +1,397 @@
+use std::f32::consts::PI;
+
+/// Width of the texture of an audio channel, the number of frequency bins and waveform samples.
+pub const WIDTH: usize = 512;
+
+/// Samples analyzed for the spectrum, of whose bins the lower [`WIDTH`] are kept like Shadertoy
+/// does.
+const FFT_SIZE: usize = 2048;
+
+/// The levels mapped to 0 and 1 in the spectrum, the defaults of the Web Audio `AnalyserNode`.
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+/// A decoded WAV file, mixed down to a single channel.
+pub struct Audio {
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl std::fmt::Debug for Audio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Audio")
+            .field("sample_rate", &self.sample_rate)
+            .field(
+                "seconds",
+                &(self.samples.len() as f32 / self.sample_rate as f32),
+            )
+            .finish()
+    }
+}
+
+impl Audio {
+    /// The sample the analysis at the playback time ends at.
+    ///
+    /// Times before the start and long after the end are clamped to where the analysis no longer
+    /// changes, so the texture of a silent channel isn't written again.
+    pub fn position(&self, time: f32) -> usize {
+        let position = (f64::from(time) * f64::from(self.sample_rate)).round();
+        (position.max(0.0) as usize).min(self.samples.len() + FFT_SIZE)
+    }
+
+    /// The texels of a [`WIDTH`]x2 RGBA texture ending at the sample position, the spectrum in the
+    /// first row and the waveform in the second.
+    ///
+    /// Both are computed from the samples up to `position` alone, without the smoothing over
+    /// previous frames Shadertoy applies, so a frame looks the same however it is reached.
+    pub fn analyze(&self, position: usize) -> Vec<u8> {
+        let sample = |count: usize, index: usize| {
+            (position + index)
+                .checked_sub(count)
+                .and_then(|index| self.samples.get(index))
+                .copied()
+                .unwrap_or_default()
+        };
+
+        let mut spectrum: Vec<(f32, f32)> = (0..FFT_SIZE)
+            .map(|index| (sample(FFT_SIZE, index) * blackman(index), 0.0))
+            .collect();
+        fft(&mut spectrum);
+
+        let spectrum = spectrum[..WIDTH].iter().map(|&(re, im)| {
+            let magnitude = (re * re + im * im).sqrt() / FFT_SIZE as f32;
+            let decibels = 20.0 * magnitude.log10();
+            (decibels - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)
+        });
+        let waveform = (0..WIDTH).map(|index| 0.5 + 0.5 * sample(WIDTH, index));
+
+        spectrum
+            .chain(waveform)
+            .flat_map(|value| {
+                let byte = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                [byte, byte, byte, 255]
+            })
+            .collect()
+    }
+}
+
+/// Whether the data starts like a WAV file.
+pub fn is_wav(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WAVE"
+}
+
+/// Decodes an integer PCM or float WAV file.
+pub fn decode(data: &[u8]) -> Result<Audio, &'static str> {
+    if !is_wav(data) {
+        return Err("not a WAV file");
+    }
+
+    let mut format = None;
+    let mut samples = None;
+    let mut chunks = &data[12..];
+    while chunks.len() >= 8 {
+        let id = &chunks[..4];
+        let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+        let body = chunks.get(8..8 + size).ok_or("truncated chunk")?;
+        match id {
+            b"fmt " => format = Some(Format::parse(body)?),
+            b"data" => samples = Some(body),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        chunks = chunks.get(8 + size + size % 2..).unwrap_or_default();
+    }
+    let format = format.ok_or("missing format")?;
+    let samples = samples.ok_or("missing samples")?;
+
+    let channels = usize::from(format.channels);
+    let frame_size = channels * usize::from(format.bits_per_sample / 8);
+    let samples = samples
+        .chunks_exact(frame_size)
+        .map(|frame| {
+            let sum: f32 = frame
+                .chunks_exact(frame_size / channels)
+                .map(|sample| format.decode(sample))
+                .sum();
+            sum / channels as f32
+        })
+        .collect();
+
+    Ok(Audio {
+        sample_rate: format.sample_rate,
+        samples,
+    })
+}
+
+struct Format {
+    is_float: bool,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+impl Format {
+    const PCM: u16 = 1;
+    const FLOAT: u16 = 3;
+    /// Stores the actual format in the first two bytes of its sub format.
+    const EXTENSIBLE: u16 = 0xfffe;
+
+    fn parse(body: &[u8]) -> Result<Self, &'static str> {
+        if body.len() < 16 {
+            return Err("truncated format");
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+
+        let tag = match u16_at(0) {
+            Self::EXTENSIBLE if body.len() >= 26 => u16_at(24),
+            tag => tag,
+        };
+        let format = Self {
+            is_float: tag == Self::FLOAT,
+            channels: u16_at(2),
+            sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+            bits_per_sample: u16_at(14),
+        };
+
+        match (tag, format.bits_per_sample) {
+            (Self::PCM, 8 | 16 | 24 | 32) | (Self::FLOAT, 32) => {}
+            _ => return Err("unsupported sample format"),
+        }
+        if format.channels == 0 || format.sample_rate == 0 {
+            return Err("no channels or sample rate");
+        }
+        Ok(format)
+    }
+
+    /// Converts a sample to the range of -1 to 1.
+    fn decode(&self, sample: &[u8]) -> f32 {
+        match (self.is_float, sample) {
+            (true, &[a, b, c, d]) => f32::from_le_bytes([a, b, c, d]),
+            // 8 bit samples are the only unsigned ones
+            (false, &[a]) => (f32::from(a) - 128.0) / 128.0,
+            (false, &[a, b]) => f32::from(i16::from_le_bytes([a, b])) / 32_768.0,
+            (false, &[a, b, c]) => i32::from_le_bytes([0, a, b, c]) as f32 / 2_147_483_648.0,
+            (false, &[a, b, c, d]) => i32::from_le_bytes([a, b, c, d]) as f32 / 2_147_483_648.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// The Blackman window the Web Audio `AnalyserNode` applies.
+fn blackman(index: usize) -> f32 {
+    let phase = 2.0 * PI * index as f32 / FFT_SIZE as f32;
+    0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+}
+
+/// In place radix 2 FFT of complex values, the length has to be a power of two.
+fn fft(values: &mut [(f32, f32)]) {
+    let len = values.len();
+
+    // Bit reversed order, so the butterflies can combine neighbouring halves
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let angle = -2.0 * PI / size as f32;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (re, im) = values[start + k + size / 2];
+                let twiddled = (re * cos - im * sin, re * sin + im * cos);
+                let even = values[start + k];
+                values[start + k] = (even.0 + twiddled.0, even.1 + twiddled.1);
+                values[start + k + size / 2] = (even.0 - twiddled.0, even.1 - twiddled.1);
+            }
+        }
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WAV file with a format chunk of the tag and the interleaved samples.
+    fn wav(tag: u16, channels: u16, bits_per_sample: u16, samples: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits_per_sample / 8;
+        let mut fmt = Vec::new();
+        fmt.extend(tag.to_le_bytes());
+        fmt.extend(channels.to_le_bytes());
+        fmt.extend(8_000u32.to_le_bytes());
+        fmt.extend((8_000 * u32::from(block_align)).to_le_bytes());
+        fmt.extend(block_align.to_le_bytes());
+        fmt.extend(bits_per_sample.to_le_bytes());
+
+        let mut chunks = Vec::new();
+        for (id, body) in [(b"fmt ", &fmt[..]), (b"data", samples)] {
+            chunks.extend(id);
+            chunks.extend((body.len() as u32).to_le_bytes());
+            chunks.extend(body);
+        }
+
+        let mut data = Vec::new();
+        data.extend(b"RIFF");
+        data.extend((4 + chunks.len() as u32).to_le_bytes());
+        data.extend(b"WAVE");
+        data.extend(chunks);
+        data
+    }
+
+    fn samples(data: &[u8]) -> Vec<f32> {
+        let audio = decode(data).unwrap();
+        assert_eq!(audio.sample_rate, 8_000);
+        audio.samples
+    }
+
+    #[test]
+    fn decodes_pcm16() {
+        let mono: Vec<u8> = [0i16, 16_384, -32_768]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        assert_eq!(samples(&wav(Format::PCM, 1, 16, &mono)), [0.0, 0.5, -1.0]);
+
+        // Stereo is mixed down to the average of the channels
+        let stereo: Vec<u8> = [16_384i16, 0, -16_384, -16_384]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        assert_eq!(samples(&wav(Format::PCM, 2, 16, &stereo)), [0.25, -0.5]);
+    }
+
+    #[test]
+    fn decodes_pcm24() {
+        let mono = [0x00, 0x00, 0x40, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00];
+        assert_eq!(samples(&wav(Format::PCM, 1, 24, &mono)), [0.5, -1.0, 0.0]);
+
+        let stereo = [0x00, 0x00, 0x40, 0x00, 0x00, 0xc0];
+        assert_eq!(samples(&wav(Format::PCM, 2, 24, &stereo)), [0.0]);
+    }
+
+    #[test]
+    fn decodes_float32() {
+        let mono: Vec<u8> = [0.75f32, -0.25]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        assert_eq!(samples(&wav(Format::FLOAT, 1, 32, &mono)), [0.75, -0.25]);
+
+        let stereo: Vec<u8> = [0.5f32, -0.25]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        assert_eq!(samples(&wav(Format::FLOAT, 2, 32, &stereo)), [0.125]);
+    }
+
+    #[test]
+    fn skips_unknown_chunks() {
+        let mut data = wav(Format::PCM, 1, 8, &[128, 255]);
+        // An odd sized chunk in front of the format, followed by its padding byte
+        data.splice(12..12, *b"LIST\x03\x00\x00\x00abc\x00");
+        assert_eq!(samples(&data), [0.0, 127.0 / 128.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let data = wav(Format::PCM, 2, 16, &[0; 64]);
+
+        // Every truncation fails or decodes fewer samples, without panicking
+        for len in 0..data.len() {
+            if let Ok(audio) = decode(&data[..len]) {
+                assert!(audio.samples.len() < 16);
+            }
+        }
+
+        assert!(decode(b"RIFF\x00\x00\x00\x00WAVE").is_err());
+        assert!(decode(&wav(Format::PCM, 0, 16, &[])).is_err());
+        assert!(decode(&wav(Format::PCM, 1, 12, &[0; 6])).is_err());
+        assert!(decode(&wav(Format::FLOAT, 1, 64, &[0; 8])).is_err());
+        assert!(decode(b"\x89PNG\r\n\x1a\n").is_err());
+    }
+
+    #[test]
+    fn fft_of_sine_peaks_at_its_frequency() {
+        let mut values: Vec<(f32, f32)> = (0..FFT_SIZE)
+            .map(|index| {
+                (
+                    (2.0 * PI * 100.0 * index as f32 / FFT_SIZE as f32).sin(),
+                    0.0,
+                )
+            })
+            .collect();
+        fft(&mut values);
+
+        let magnitudes: Vec<f32> = values[..FFT_SIZE / 2]
+            .iter()
+            .map(|(re, im)| (re * re + im * im).sqrt())
+            .collect();
+        let peak = (0..magnitudes.len())
+            .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+            .unwrap();
+        assert_eq!(peak, 100);
+        // A sine of amplitude 1 has half of its energy in the positive frequency
+        assert!((magnitudes[100] - FFT_SIZE as f32 / 2.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn analyzes_spectrum_and_waveform() {
+        // A sine falling on bin 64, quiet enough not to saturate the spectrum
+        let audio = Audio {
+            sample_rate: FFT_SIZE as u32,
+            samples: (0..2 * FFT_SIZE)
+                .map(|index| 0.001 * (2.0 * PI * 64.0 * index as f32 / FFT_SIZE as f32).sin())
+                .collect(),
+        };
+        let texels = audio.analyze(audio.position(1.5));
+        assert_eq!(texels.len(), 4 * 2 * WIDTH);
+
+        let spectrum: Vec<u8> = texels[..4 * WIDTH].iter().step_by(4).copied().collect();
+        // The Blackman window has a coherent gain of 0.42, so the bin is at -73.6 dB
+        assert_eq!(spectrum[64], 96);
+        assert!(spectrum[..60]
+            .iter()
+            .chain(&spectrum[68..])
+            .all(|&byte| byte < 10));
+        assert_eq!(texels[4 * 64 + 3], 255);
+
+        let waveform: Vec<u8> = texels[4 * WIDTH..].iter().step_by(4).copied().collect();
+        assert!(waveform.iter().all(|&byte| byte == 127 || byte == 128));
+    }
+
+    #[test]
+    fn analyzes_silence_outside_of_the_samples() {
+        let audio = Audio {
+            sample_rate: 1_000,
+            samples: vec![1.0; 1_000],
+        };
+        assert_eq!(audio.position(-1.0), 0);
+        assert_eq!(audio.position(0.5), 500);
+        assert_eq!(audio.position(100.0), 1_000 + FFT_SIZE);
+
+        for position in [0, audio.position(100.0)] {
+            let texels = audio.analyze(position);
+            assert!(texels[..4 * WIDTH]
+                .chunks(4)
+                .all(|texel| texel == [0, 0, 0, 255]));
+            assert!(texels[4 * WIDTH..]
+                .chunks(4)
+                .all(|texel| texel == [128, 128, 128, 255]));
+        }
+
+        // The waveform ends at the position
+        let waveform = audio.analyze(1_000 + WIDTH / 2);
+        assert_eq!(waveform[4 * WIDTH + 4 * (WIDTH / 2 - 1)], 255);
+        assert_eq!(waveform[4 * WIDTH + 4 * (WIDTH / 2)], 128);
+    }
+}
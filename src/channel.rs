@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+use crate::audio::{self, Audio};
+use crate::util;
+
+/// Number of image inputs a shader can sample.
+pub const CHANNEL_COUNT: usize = 4;
+
+/// An image or audio file bound to a shader as `channelN`.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub input: Input,
+    pub sampler: Sampler,
+}
+
+#[derive(Debug, Clone)]
+pub enum Input {
+    Image(Arc<ChannelImage>),
+    /// Analyzed every frame into a texture like Shadertoy's audio inputs, with the spectrum in the
+    /// first row and the waveform in the second.
+    Audio(Arc<Audio>),
+}
+
+/// A decoded RGBA image together with its mip chain, largest level first.
+pub struct ChannelImage {
+    levels: Vec<image::RgbaImage>,
+}
+
+impl ChannelImage {
+    pub fn levels(&self) -> &[image::RgbaImage] {
+        &self.levels
+    }
+
+    /// Whether the image was decoded with all levels down to 1x1.
+    pub fn has_mip_chain(&self) -> bool {
+        self.levels.len() > 1 || self.levels[0].dimensions() == (1, 1)
+    }
+}
+
+impl std::fmt::Debug for ChannelImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.levels[0].dimensions();
+        f.debug_struct("ChannelImage")
+            .field("width", &width)
+            .field("height", &height)
+            .field("levels", &self.levels.len())
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sampler {
+    pub wrap: Wrap,
+    pub filter: Filter,
+    pub mipmaps: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl Wrap {
+    pub const ALL: [Wrap; 3] = [Wrap::Repeat, Wrap::Clamp, Wrap::Mirror];
+}
+
+impl std::fmt::Display for Wrap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Repeat => "Repeat",
+            Self::Clamp => "Clamp",
+            Self::Mirror => "Mirror",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 2] = [Filter::Nearest, Filter::Linear];
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Nearest => "Nearest",
+            Self::Linear => "Linear",
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum Error {
+    #[error("Could not decode image: {0}")]
+    Image(Arc<image::ImageError>),
+    #[error("Could not decode audio: {0}")]
+    Audio(&'static str),
+    #[error(transparent)]
+    Blocking(#[from] util::Error),
+}
+
+/// Decodes a WAV file, or a PNG or JPEG file and builds its mip chain if `mipmaps` is set.
+///
+/// Blocks until done, which takes a while for large images.
+pub fn decode(data: &[u8], mipmaps: bool) -> Result<Input, Error> {
+    if audio::is_wav(data) {
+        return audio::decode(data)
+            .map(|audio| Input::Audio(Arc::new(audio)))
+            .map_err(Error::Audio);
+    }
+
+    let image = image::load_from_memory(data)
+        .map_err(|e| Error::Image(Arc::new(e)))?
+        .to_rgba8();
+
+    let mut levels = vec![image];
+    while mipmaps && levels[levels.len() - 1].dimensions() != (1, 1) {
+        let (width, height) = levels[levels.len() - 1].dimensions();
+        let level = image::imageops::resize(
+            &levels[levels.len() - 1],
+            (width / 2).max(1),
+            (height / 2).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        levels.push(level);
+    }
+
+    Ok(Input::Image(Arc::new(ChannelImage { levels })))
+}
@@ -0,0 +1,257 @@
+use crate::channel::{self, Channel, Filter, Input, Sampler, Wrap, CHANNEL_COUNT};
+use crate::shader_update::ShaderUpdate;
+use crate::util::{self, FileName};
+
+use iced::widget::{button, column, pick_list, row, text, toggler};
+use iced::{Center, Element, Length, Task};
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open(usize),
+    OpenAudio(usize),
+    Opened(usize, Result<(FileName, Arc<Vec<u8>>), util::Error>),
+    Decoded(usize, Arc<Vec<u8>>, Result<Input, channel::Error>),
+    Clear(usize),
+    WrapSelected(usize, Wrap),
+    FilterSelected(usize, Filter),
+    MipmapsToggled(usize, bool),
+    Update(ShaderUpdate),
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ChannelsEditor {
+    channels: [Option<EditorChannel>; CHANNEL_COUNT],
+    /// Why the file last opened in a channel was dropped, shown until another one is opened.
+    #[serde(skip)]
+    errors: [Option<String>; CHANNEL_COUNT],
+}
+
+#[derive(Serialize, Deserialize)]
+struct EditorChannel {
+    file: FileName,
+    /// The encoded image or audio file, stored in the project so it doesn't depend on the
+    /// original file.
+    #[serde(with = "encoded")]
+    data: Arc<Vec<u8>>,
+    sampler: Sampler,
+    #[serde(skip)]
+    input: Option<Input>,
+}
+
+impl EditorChannel {
+    fn channel(&self) -> Option<Channel> {
+        Some(Channel {
+            input: self.input.clone()?,
+            sampler: self.sampler,
+        })
+    }
+}
+
+impl ChannelsEditor {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Open(index) => Task::perform(
+                util::open_binary("Open an image...", "Image", &["png", "jpg", "jpeg"]),
+                move |result| Message::Opened(index, result),
+            ),
+            Message::OpenAudio(index) => Task::perform(
+                util::open_binary("Open an audio file...", "Audio", &["wav"]),
+                move |result| Message::Opened(index, result),
+            ),
+            Message::Opened(index, result) => {
+                let Ok((file, data)) = result else {
+                    return Task::none();
+                };
+
+                let sampler = Sampler::default();
+                self.channels[index] = Some(EditorChannel {
+                    file,
+                    data: data.clone(),
+                    sampler,
+                    input: None,
+                });
+                self.errors[index] = None;
+
+                decode(index, data, sampler.mipmaps)
+            }
+            Message::Decoded(index, data, result) => {
+                // Ignore files that were replaced while they were decoded
+                let Some(channel) = &mut self.channels[index] else {
+                    return Task::none();
+                };
+                if !Arc::ptr_eq(&channel.data, &data) {
+                    return Task::none();
+                }
+
+                match result {
+                    Ok(input) => channel.input = Some(input),
+                    Err(error) => {
+                        self.errors[index] = Some(format!(
+                            "{}: {error}",
+                            channel.file.as_str().unwrap_or_default()
+                        ));
+                        self.channels[index] = None;
+                    }
+                }
+
+                Task::done(self.update_pipeline(index))
+            }
+            Message::Clear(index) => {
+                self.channels[index] = None;
+                self.errors[index] = None;
+                Task::done(self.update_pipeline(index))
+            }
+            Message::WrapSelected(index, wrap) => {
+                self.update_sampler(index, |sampler| sampler.wrap = wrap)
+            }
+            Message::FilterSelected(index, filter) => {
+                self.update_sampler(index, |sampler| sampler.filter = filter)
+            }
+            Message::MipmapsToggled(index, mipmaps) => {
+                let update = self.update_sampler(index, |sampler| sampler.mipmaps = mipmaps);
+
+                // Images are decoded without their mip chain until it's needed
+                match &self.channels[index] {
+                    Some(EditorChannel {
+                        data,
+                        input: Some(Input::Image(image)),
+                        ..
+                    }) if mipmaps && !image.has_mip_chain() => {
+                        Task::batch([update, decode(index, data.clone(), true)])
+                    }
+                    _ => update,
+                }
+            }
+            Message::Update(_) => Task::none(),
+        }
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        column(self.channels.iter().enumerate().map(|(index, channel)| {
+            let label = text(format!("channel{index}")).width(80.0);
+
+            if let Some(channel) = channel {
+                let mut controls = row![
+                    label,
+                    text(channel.file.as_str().unwrap_or_default().to_string()).width(Length::Fill),
+                    pick_list(Wrap::ALL, Some(channel.sampler.wrap), move |wrap| {
+                        Message::WrapSelected(index, wrap)
+                    }),
+                    pick_list(Filter::ALL, Some(channel.sampler.filter), move |filter| {
+                        Message::FilterSelected(index, filter)
+                    }),
+                ];
+                // The texture of an audio channel has no mip chain
+                if !matches!(channel.input, Some(Input::Audio(_))) {
+                    controls = controls.push(
+                        toggler(channel.sampler.mipmaps)
+                            .label("Mipmaps")
+                            .on_toggle(move |mipmaps| Message::MipmapsToggled(index, mipmaps)),
+                    );
+                }
+                controls
+                    .push(button("X").on_press(Message::Clear(index)))
+                    .spacing(5)
+                    .align_y(Center)
+                    .into()
+            } else {
+                let mut controls = row![
+                    label,
+                    button("Open image").on_press(Message::Open(index)),
+                    button("Open audio").on_press(Message::OpenAudio(index)),
+                ];
+                if let Some(error) = &self.errors[index] {
+                    controls = controls.push(text(error.as_str()).style(text::danger));
+                }
+                controls.spacing(5).align_y(Center).into()
+            }
+        }))
+        .into()
+    }
+
+    /// Decodes the stored images of an opened project.
+    pub fn reload(&self) -> Task<Message> {
+        Task::batch(
+            self.channels
+                .iter()
+                .enumerate()
+                .filter_map(|(index, channel)| {
+                    let channel = channel.as_ref()?;
+                    Some(decode(index, channel.data.clone(), channel.sampler.mipmaps))
+                }),
+        )
+    }
+
+    /// Decodes the stored files in place, for use outside of the application's runtime.
+    pub fn decode(&mut self) -> Result<(), channel::Error> {
+        for channel in self.channels.iter_mut().flatten() {
+            channel.input = Some(channel::decode(&channel.data, channel.sampler.mipmaps)?);
+        }
+        Ok(())
+    }
+
+    /// The updates binding the decoded images to the pipeline.
+    pub fn updates(&self) -> Vec<ShaderUpdate> {
+        (0..CHANNEL_COUNT)
+            .map(|index| self.shader_update(index))
+            .collect()
+    }
+
+    fn update_sampler(&mut self, index: usize, update: impl FnOnce(&mut Sampler)) -> Task<Message> {
+        if let Some(channel) = &mut self.channels[index] {
+            update(&mut channel.sampler);
+            Task::done(self.update_pipeline(index))
+        } else {
+            Task::none()
+        }
+    }
+
+    fn update_pipeline(&self, index: usize) -> Message {
+        Message::Update(self.shader_update(index))
+    }
+
+    fn shader_update(&self, index: usize) -> ShaderUpdate {
+        ShaderUpdate::Channel(
+            index,
+            self.channels[index]
+                .as_ref()
+                .and_then(EditorChannel::channel),
+        )
+    }
+}
+
+fn decode(index: usize, data: Arc<Vec<u8>>, mipmaps: bool) -> Task<Message> {
+    let encoded = data.clone();
+    Task::perform(
+        async move {
+            util::spawn_blocking(move || channel::decode(&encoded, mipmaps))
+                .await
+                .unwrap_or_else(|error| Err(error.into()))
+        },
+        move |result| Message::Decoded(index, data, result),
+    )
+}
+
+mod encoded {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use std::sync::Arc;
+
+    pub fn serialize<S: Serializer>(data: &Arc<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data.as_slice()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<Vec<u8>>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map(Arc::new)
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use iced::Size;
+
+use crate::channel;
+use crate::editor::Editor;
+use crate::viewer::headless;
+
+const USAGE: &str =
+    "Usage: bulin render <project.bulin> [--size <WIDTHxHEIGHT>] [--time <SECONDS>] [-o <out.png>]";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}\n{USAGE}")]
+    Usage(String),
+    #[error("Could not read project: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse project: {0}")]
+    Project(#[from] serde_json::Error),
+    #[error(transparent)]
+    Channel(#[from] channel::Error),
+    #[error(transparent)]
+    Render(#[from] headless::Error),
+    #[error("Could not write image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Options of `bulin render`.
+#[derive(Debug)]
+pub struct Render {
+    project: PathBuf,
+    size: Size<u32>,
+    time: Duration,
+    output: PathBuf,
+}
+
+impl Render {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Error> {
+        let mut project = None;
+        let mut size = Size::new(1920, 1080);
+        let mut time = Duration::ZERO;
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| Error::Usage(format!("Missing value for {name}")))
+            };
+
+            match arg.as_str() {
+                "--size" => {
+                    let value = value("--size")?;
+                    size = value
+                        .split_once('x')
+                        .and_then(|(width, height)| {
+                            Some(Size::new(width.parse().ok()?, height.parse().ok()?))
+                        })
+                        .ok_or_else(|| Error::Usage(format!("Invalid size {value}")))?;
+                }
+                "--time" => {
+                    let value = value("--time")?;
+                    time = value
+                        .parse()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f32(seconds).ok())
+                        .ok_or_else(|| Error::Usage(format!("Invalid time {value}")))?;
+                }
+                "-o" | "--output" => output = Some(PathBuf::from(value("--output")?)),
+                _ if project.is_none() && !arg.starts_with('-') => {
+                    project = Some(PathBuf::from(arg))
+                }
+                _ => return Err(Error::Usage(format!("Unexpected argument {arg}"))),
+            }
+        }
+
+        let project: PathBuf =
+            project.ok_or_else(|| Error::Usage(String::from("Missing project")))?;
+        let output = output.unwrap_or_else(|| project.with_extension("png"));
+
+        Ok(Self {
+            project,
+            size,
+            time,
+            output,
+        })
+    }
+
+    /// Renders the image pass of the project at the given time and writes it to the output file.
+    pub fn run(self) -> Result<(), Error> {
+        let mut editor = Editor::open(&std::fs::read_to_string(&self.project)?)?;
+        editor.decode_channels()?;
+
+        iced::futures::executor::block_on(async {
+            let mut renderer = headless::Renderer::new(editor.updates(), self.size).await?;
+            renderer.seek(self.time);
+            let image = renderer.render()?;
+            image.save(&self.output)?;
+
+            Ok(())
+        })
+    }
+}
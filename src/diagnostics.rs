@@ -0,0 +1,65 @@
+use iced::widget::{button, column, scrollable, text, Button, Column};
+use iced::{Element, Length};
+
+use crate::shader_update::Pass;
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub pass: Pass,
+    pub message: String,
+    /// Position within the user shader, `None` if the error cannot be attributed to a line of it.
+    pub location: Option<Location>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some(location) => write!(
+                f,
+                "{} {}:{}: {}",
+                self.pass, location.line, location.column, self.message
+            ),
+            None => write!(f, "{} {}", self.pass, self.message),
+        }
+    }
+}
+
+/// 1-based line and column of a position in the user shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Selected(Pass, Location),
+}
+
+pub fn view(diagnostics: &[Diagnostic]) -> Element<'_, Message> {
+    if diagnostics.is_empty() {
+        return text("No errors").into();
+    }
+
+    scrollable(list(diagnostics))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+/// The diagnostics without a scrollable, for embedding them into other lists.
+pub fn list(diagnostics: &[Diagnostic]) -> Column<'_, Message> {
+    column(diagnostics.iter().map(|d| diagnostic(d).into()))
+}
+
+fn diagnostic(diagnostic: &Diagnostic) -> Button<'_, Message> {
+    let button = button(text(diagnostic.to_string()))
+        .style(button::text)
+        .width(Length::Fill);
+
+    if let Some(location) = diagnostic.location {
+        button.on_press(Message::Selected(diagnostic.pass, location))
+    } else {
+        button
+    }
+}
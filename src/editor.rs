@@ -1,41 +1,126 @@
-use crate::shader_update::ShaderUpdate;
+use crate::channel;
+use crate::channels_editor;
+use crate::diagnostics::{Diagnostic, Location};
+use crate::shader_update::{Language, Pass, ShaderUpdate, UniformsUpdate};
 use crate::text_editor;
 use crate::uniforms_editor;
+use crate::viewer::camera::Camera;
+use crate::viewer::output::Output;
+use crate::viewer::transport::Transport;
+use crate::viewer::{self, shadertoy};
 
-use iced::widget::container;
-use iced::Subscription;
-use iced::{Element, Task};
+use iced::widget::{button, container, horizontal_space, pick_list, row, scrollable, text};
+use iced::{Center, Element, Fill, Font, Task};
 use serde::{Deserialize, Serialize};
 
+use std::collections::BTreeMap;
+
 #[derive(Debug, Clone)]
 pub enum Message {
+    /// Message for the text editor of the selected pass.
     TextEditor(text_editor::Message),
+    Pass(Pass, text_editor::Message),
     UniformsEditor(uniforms_editor::Message),
+    ChannelsEditor(channels_editor::Message),
     UpdatePipeline(ShaderUpdate),
+    SelectPass(Pass),
+    AddBuffer,
+    RemoveBuffer(Pass),
+    GoTo(Pass, Location),
+    LanguageSelected(Language),
+    /// Switches to the selected language even though the passes are written in the current one.
+    ConfirmLanguage,
+    CancelLanguage,
+    /// Shows or hides the declarations prepended to the shaders in place of the text editor.
+    TogglePrelude,
+    /// Replaces the image pass with a shader translated from Shadertoy.
+    Import(shadertoy::Imported),
     ProjectOpened,
 }
 
+/// The state of the viewer that is saved with a project.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub transport: Transport,
+    pub output: Output,
+    pub camera: Camera,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Editor {
+    /// The image pass.
     text_editor: text_editor::TextEditor,
+    #[serde(default)]
+    buffers: BTreeMap<Pass, text_editor::TextEditor>,
+    #[serde(skip)]
+    selected: Pass,
     uniforms_editor: uniforms_editor::UniformsEditor,
+    #[serde(default)]
+    language: Language,
+    #[serde(default)]
+    channels_editor: channels_editor::ChannelsEditor,
+    /// The timeline of the viewer when the project was last saved.
+    #[serde(default)]
+    transport: Transport,
+    /// How the viewer showed the image pass when the project was last saved.
+    #[serde(default)]
+    output: Output,
+    /// Where the camera of the viewer was when the project was last saved.
+    #[serde(default)]
+    camera: Camera,
+    #[serde(skip)]
+    show_prelude: bool,
+    /// The language selected while the passes have code, until the switch is confirmed.
+    #[serde(skip)]
+    pending_language: Option<Language>,
 }
 
 impl Editor {
     pub fn new(shader: &str) -> Self {
         Self {
             text_editor: text_editor::TextEditor::new(shader),
+            buffers: BTreeMap::new(),
+            selected: Pass::Image,
             uniforms_editor: uniforms_editor::UniformsEditor::new(),
+            language: Language::default(),
+            channels_editor: channels_editor::ChannelsEditor::default(),
+            transport: Transport::default(),
+            output: Output::default(),
+            camera: Camera::default(),
+            show_prelude: false,
+            pending_language: None,
         }
     }
 
+    /// Parses a saved project, upgrading projects saved by earlier versions.
+    pub fn open(json: &str) -> serde_json::Result<Self> {
+        let mut editor: Self = serde_json::from_str(json)?;
+
+        // Time used to be the hidden custom uniform `time`
+        if editor.uniforms_editor.remove_legacy_time() {
+            for text_editor in
+                std::iter::once(&mut editor.text_editor).chain(editor.buffers.values_mut())
+            {
+                text_editor.replace("customs.time", "uniforms.time");
+            }
+        }
+
+        Ok(editor)
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::TextEditor(message) => match message {
+            Message::TextEditor(message) => Task::done(Message::Pass(self.selected, message)),
+            Message::Pass(pass, message) => match message {
                 text_editor::Message::UpdatePipeline(shader) => {
-                    Task::done(Message::UpdatePipeline(ShaderUpdate::Shader(shader)))
+                    Task::done(Message::UpdatePipeline(ShaderUpdate::Shader(pass, shader)))
                 }
-                _ => self.text_editor.update(message).map(Message::TextEditor),
+                _ => match self.pass_mut(pass) {
+                    Some(text_editor) => text_editor
+                        .update(message)
+                        .map(move |message| Message::Pass(pass, message)),
+                    None => Task::none(),
+                },
             },
             Message::UniformsEditor(message) => match message {
                 uniforms_editor::Message::Update(message) => {
@@ -46,31 +131,355 @@ impl Editor {
                     .update(message)
                     .map(Message::UniformsEditor),
             },
-            Message::ProjectOpened => Task::done(Message::UpdatePipeline(ShaderUpdate::Shader(
-                self.text_editor.content(),
-            )))
-            .chain(Task::done(Message::UpdatePipeline(ShaderUpdate::Uniforms(
-                crate::shader_update::UniformsUpdate::Reset(self.uniforms_editor.uniforms()),
-            )))),
+            Message::ChannelsEditor(message) => match message {
+                channels_editor::Message::Update(message) => {
+                    Task::done(Message::UpdatePipeline(message))
+                }
+                _ => self
+                    .channels_editor
+                    .update(message)
+                    .map(Message::ChannelsEditor),
+            },
+            Message::SelectPass(pass) => {
+                if self.pass_mut(pass).is_some() {
+                    self.selected = pass;
+                }
+                Task::none()
+            }
+            Message::AddBuffer => {
+                let Some(pass) = Pass::BUFFERS
+                    .into_iter()
+                    .find(|pass| !self.buffers.contains_key(pass))
+                else {
+                    return Task::none();
+                };
+
+                let text_editor = text_editor::TextEditor::new(template(self.language));
+                let shader = text_editor.content();
+                self.buffers.insert(pass, text_editor);
+                self.selected = pass;
+
+                Task::done(Message::UpdatePipeline(ShaderUpdate::Shader(pass, shader)))
+            }
+            Message::RemoveBuffer(pass) => {
+                if self.buffers.remove(&pass).is_none() {
+                    return Task::none();
+                }
+                if self.selected == pass {
+                    self.selected = Pass::Image;
+                }
+
+                Task::done(Message::UpdatePipeline(ShaderUpdate::RemovePass(pass)))
+            }
+            Message::GoTo(pass, location) => {
+                if self.pass_mut(pass).is_none() {
+                    return Task::none();
+                }
+                self.selected = pass;
+
+                Task::done(Message::Pass(pass, text_editor::Message::GoTo(location)))
+            }
+            Message::LanguageSelected(language) => {
+                if language == self.language {
+                    self.pending_language = None;
+                    return Task::none();
+                }
+
+                // Only the template is translated, so other code needs the switch to be confirmed
+                let has_code = Pass::ALL
+                    .into_iter()
+                    .filter_map(|pass| self.pass(pass))
+                    .any(|text_editor| {
+                        let content = text_editor.content();
+                        let content = content.trim();
+                        !content.is_empty() && content != template(self.language).trim()
+                    });
+                if has_code {
+                    self.pending_language = Some(language);
+                    Task::none()
+                } else {
+                    self.switch_language(language)
+                }
+            }
+            Message::ConfirmLanguage => match self.pending_language.take() {
+                Some(language) => self.switch_language(language),
+                None => Task::none(),
+            },
+            Message::CancelLanguage => {
+                self.pending_language = None;
+                Task::none()
+            }
+            Message::TogglePrelude => {
+                self.show_prelude = !self.show_prelude;
+                Task::none()
+            }
+            Message::Import(imported) => {
+                self.text_editor = text_editor::TextEditor::new(&imported.shader);
+                self.selected = Pass::Image;
+
+                Task::done(Message::UpdatePipeline(ShaderUpdate::Shader(
+                    Pass::Image,
+                    imported.shader,
+                )))
+            }
+            Message::ProjectOpened => Task::batch(
+                self.updates()
+                    .into_iter()
+                    .map(|update| Task::done(Message::UpdatePipeline(update))),
+            )
+            .chain(self.channels_editor.reload().map(Message::ChannelsEditor)),
             Message::UpdatePipeline(_) => Task::none(),
         }
     }
 
     pub fn view(&self) -> Element<Message> {
-        container(iced::widget::column![
+        let selected = self.selected;
+        let mut tabs = row(std::iter::once(Pass::Image)
+            .chain(self.buffers.keys().copied())
+            .map(|pass| {
+                button(text(pass.to_string()))
+                    .style(if pass == selected {
+                        button::primary
+                    } else {
+                        button::secondary
+                    })
+                    .on_press(Message::SelectPass(pass))
+                    .into()
+            }))
+        .spacing(5);
+
+        if self.buffers.len() < Pass::BUFFERS.len() {
+            tabs = tabs.push(button(text("+")).on_press(Message::AddBuffer));
+        }
+        tabs = tabs
+            .push(horizontal_space())
+            .push(
+                button(text("Prelude"))
+                    .style(if self.show_prelude {
+                        button::primary
+                    } else {
+                        button::secondary
+                    })
+                    .on_press(Message::TogglePrelude),
+            )
+            .push(pick_list(
+                Language::ALL,
+                Some(self.pending_language.unwrap_or(self.language)),
+                Message::LanguageSelected,
+            ));
+        if selected != Pass::Image {
+            tabs = tabs.push(
+                button(text("Remove buffer"))
+                    .style(button::danger)
+                    .on_press(Message::RemoveBuffer(selected)),
+            );
+        }
+
+        let content = if self.show_prelude {
+            container(scrollable(
+                text(viewer::prelude(
+                    &self.uniforms_editor.uniforms(),
+                    self.language,
+                ))
+                .font(Font::MONOSPACE)
+                .size(14),
+            ))
+            .height(Fill)
+            .padding(10)
+            .into()
+        } else {
+            self.text()
+                .view(self.language)
+                .map(move |message| Message::Pass(selected, message))
+        };
+
+        let mut editor = iced::widget::column![
             self.uniforms_editor.view().map(Message::UniformsEditor),
-            self.text_editor.view().map(Message::TextEditor),
-        ])
-        .into()
+            self.channels_editor.view().map(Message::ChannelsEditor),
+            container(tabs).padding([0, 10]),
+        ];
+        if let Some(language) = self.pending_language {
+            editor = editor.push(
+                container(
+                    row![
+                        text(format!(
+                            "The passes are written in {}, they won't compile as {language} \
+                             until they are rewritten",
+                            self.language
+                        ))
+                        .style(text::danger),
+                        horizontal_space(),
+                        button("Cancel")
+                            .style(button::secondary)
+                            .on_press(Message::CancelLanguage),
+                        button(text(format!("Switch to {language}")))
+                            .style(button::danger)
+                            .on_press(Message::ConfirmLanguage),
+                    ]
+                    .spacing(5)
+                    .align_y(Center),
+                )
+                .padding([0, 10]),
+            );
+        }
+
+        container(editor.push(content)).into()
     }
 
+    /// The text editor of the selected pass.
     pub fn text(&self) -> &text_editor::TextEditor {
-        &self.text_editor
+        self.pass(self.selected).unwrap_or(&self.text_editor)
     }
 
-    pub fn subscription(&self) -> Subscription<Message> {
-        self.uniforms_editor
-            .subscription()
-            .map(Message::UniformsEditor)
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Whether the image pass has no code that an import would replace.
+    pub fn is_image_empty(&self) -> bool {
+        self.text_editor.content().trim().is_empty()
+    }
+
+    /// Keeps the state of the viewer to save it with the project.
+    pub fn set_snapshot(&mut self, snapshot: Snapshot) {
+        self.transport = snapshot.transport;
+        self.output = snapshot.output;
+        self.camera = snapshot.camera;
+    }
+
+    /// Decodes the channel files of a project loaded outside of the application's runtime.
+    pub fn decode_channels(&mut self) -> Result<(), channel::Error> {
+        self.channels_editor.decode()
+    }
+
+    /// The updates replacing the language, uniforms, passes, channels, timeline, output and
+    /// camera of a pipeline with the ones of this project.
+    ///
+    /// Channels whose file isn't decoded yet are left empty.
+    pub fn updates(&self) -> Vec<ShaderUpdate> {
+        self.updates_with(&Snapshot {
+            transport: self.transport.clone(),
+            output: self.output,
+            camera: self.camera,
+        })
+    }
+
+    /// The updates of this project with the timeline, output and camera of the snapshot instead
+    /// of the saved ones.
+    pub fn updates_with(&self, snapshot: &Snapshot) -> Vec<ShaderUpdate> {
+        [
+            ShaderUpdate::Language(self.language),
+            ShaderUpdate::Uniforms(UniformsUpdate::Reset(self.uniforms_editor.uniforms())),
+        ]
+        .into_iter()
+        .chain(Pass::ALL.into_iter().map(|pass| match self.pass(pass) {
+            Some(text_editor) => ShaderUpdate::Shader(pass, text_editor.content()),
+            None => ShaderUpdate::RemovePass(pass),
+        }))
+        .chain(self.channels_editor.updates())
+        .chain([
+            ShaderUpdate::Transport(snapshot.transport.clone()),
+            ShaderUpdate::Output(snapshot.output),
+            ShaderUpdate::Camera(snapshot.camera),
+        ])
+        .collect()
+    }
+
+    /// The image pass converted to GLSL for Shadertoy, with the current custom uniform values and
+    /// the camera.
+    pub fn shadertoy(&self, camera: &Camera) -> Result<String, Vec<Diagnostic>> {
+        shadertoy::export(
+            &self.text_editor.content(),
+            &self.uniforms_editor.uniforms(),
+            camera,
+            self.language,
+        )
+    }
+
+    /// Changes the language the passes are compiled as, which recompiles all of them, and
+    /// replaces the template in passes that still hold it with the one of the language.
+    fn switch_language(&mut self, language: Language) -> Task<Message> {
+        let from = template(self.language).trim();
+        self.language = language;
+
+        let mut updates = vec![ShaderUpdate::Language(language)];
+        for pass in Pass::ALL {
+            if let Some(text_editor) = self.pass_mut(pass) {
+                if text_editor.content().trim() == from {
+                    *text_editor = text_editor::TextEditor::new(template(language));
+                    updates.push(ShaderUpdate::Shader(pass, text_editor.content()));
+                }
+            }
+        }
+
+        Task::batch(
+            updates
+                .into_iter()
+                .map(|update| Task::done(Message::UpdatePipeline(update))),
+        )
+    }
+
+    fn pass(&self, pass: Pass) -> Option<&text_editor::TextEditor> {
+        match pass {
+            Pass::Image => Some(&self.text_editor),
+            _ => self.buffers.get(&pass),
+        }
+    }
+
+    fn pass_mut(&mut self, pass: Pass) -> Option<&mut text_editor::TextEditor> {
+        match pass {
+            Pass::Image => Some(&mut self.text_editor),
+            _ => self.buffers.get_mut(&pass),
+        }
+    }
+}
+
+/// The code passes start with.
+fn template(language: Language) -> &'static str {
+    match language {
+        Language::Wgsl => include_str!("viewer/canvasscene/shaders/empty_frag.wgsl"),
+        Language::Glsl => include_str!("viewer/canvasscene/shaders/empty_frag.glsl"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_migrates_legacy_time_only() {
+        let project = serde_json::json!({
+            "text_editor": {
+                "file": null,
+                "content": "let t = customs.time * customs.time_scale + customs.time;",
+                "word_wrap": true,
+            },
+            "uniforms_editor": {
+                "uniforms": [
+                    {
+                        "value": { "value": { "Float": 0.0 }, "name": "time" },
+                        "visible": false,
+                    },
+                    {
+                        "value": { "value": { "Float": 2.0 }, "name": "time_scale" },
+                        "visible": true,
+                    },
+                ],
+            },
+        });
+
+        let editor = Editor::open(&project.to_string()).unwrap();
+
+        assert_eq!(
+            editor.text_editor.content().trim_end(),
+            "let t = uniforms.time * customs.time_scale + uniforms.time;"
+        );
+        let names: Vec<String> = editor
+            .uniforms_editor
+            .uniforms()
+            .into_iter()
+            .map(|uniform| uniform.name)
+            .collect();
+        assert_eq!(names, ["time_scale"]);
     }
 }
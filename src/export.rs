@@ -0,0 +1,361 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::widget::{button, column, horizontal_space, pick_list, row, text};
+use iced::{Center, Element, Size, Task};
+use iced_aw::number_input;
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use crate::shader_update::ShaderUpdate;
+use crate::util;
+use crate::viewer::headless;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    WidthChanged(u32),
+    HeightChanged(u32),
+    FpsChanged(u32),
+    FramesChanged(u32),
+    StartChanged(f32),
+    FormatSelected(Format),
+    /// Handled by the application, which passes the project's updates to [`Export::start`].
+    Start,
+    Exported(Result<PathBuf, Error>),
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum Error {
+    #[error(transparent)]
+    File(#[from] util::Error),
+    #[error(transparent)]
+    Render(Arc<headless::Error>),
+    #[error("Could not write image: {0}")]
+    Image(Arc<image::ImageError>),
+    #[error("Could not write animation: {0}")]
+    Apng(Arc<png::EncodingError>),
+    #[error("Could not write file: {0}")]
+    Io(Arc<io::Error>),
+    #[error("Export failed unexpectedly: {0}")]
+    Panicked(Arc<tokio::task::JoinError>),
+}
+
+impl From<headless::Error> for Error {
+    fn from(error: headless::Error) -> Self {
+        Self::Render(Arc::new(error))
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Self {
+        Self::Image(Arc::new(error))
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Self {
+        Self::Apng(Arc::new(error))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(error: tokio::task::JoinError) -> Self {
+        Self::Panicked(Arc::new(error))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    PngSequence,
+    Gif,
+    Apng,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::PngSequence, Format::Gif, Format::Apng];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Format::PngSequence => "frame.png",
+            Format::Gif => "animation.gif",
+            Format::Apng => "animation.png",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::PngSequence => "PNG sequence",
+            Format::Gif => "Animated GIF",
+            Format::Apng => "Animated PNG",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    width: u32,
+    height: u32,
+    fps: u32,
+    frames: u32,
+    /// Time of the first frame in seconds.
+    start: f32,
+    format: Format,
+}
+
+/// The progress of an export, shared by the export dialogs.
+#[derive(Default)]
+pub enum State {
+    #[default]
+    Idle,
+    Exporting,
+    Finished(Result<PathBuf, Error>),
+}
+
+impl State {
+    /// Marks the export as running, returns `false` if one already is.
+    pub fn start(&mut self) -> bool {
+        if self.is_exporting() {
+            return false;
+        }
+        *self = State::Exporting;
+        true
+    }
+
+    /// Shows the result of the export, closing the file dialog isn't one.
+    pub fn finish(&mut self, result: Result<PathBuf, Error>) {
+        *self = match result {
+            Err(Error::File(util::Error::DialogClosed)) => State::Idle,
+            result => State::Finished(result),
+        };
+    }
+
+    pub fn is_exporting(&self) -> bool {
+        matches!(self, State::Exporting)
+    }
+}
+
+/// Renders a fixed number of frames at a fixed timestep, independent of the viewer's clock.
+pub struct Export {
+    settings: Settings,
+    state: State,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            settings: Settings {
+                width: 640,
+                height: 360,
+                fps: 30,
+                frames: 90,
+                start: 0.0,
+                format: Format::default(),
+            },
+            state: State::default(),
+        }
+    }
+}
+
+impl Export {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let settings = &mut self.settings;
+        match message {
+            Message::WidthChanged(width) => settings.width = width,
+            Message::HeightChanged(height) => settings.height = height,
+            Message::FpsChanged(fps) => settings.fps = fps,
+            Message::FramesChanged(frames) => settings.frames = frames,
+            Message::StartChanged(start) => settings.start = start,
+            Message::FormatSelected(format) => settings.format = format,
+            Message::Start => {}
+            Message::Exported(result) => self.state.finish(result),
+        }
+        Task::none()
+    }
+
+    /// Renders the scene described by the updates and writes it to a file picked by the user.
+    pub fn start(&mut self, updates: Vec<ShaderUpdate>) -> Task<Message> {
+        if !self.state.start() {
+            return Task::none();
+        }
+
+        let settings = self.settings;
+        Task::perform(
+            export(settings.format.file_name(), move |path| {
+                iced::futures::executor::block_on(render(settings, updates, path))
+            }),
+            Message::Exported,
+        )
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let settings = &self.settings;
+
+        dialog(
+            [
+                setting(
+                    "Width",
+                    number_input(&settings.width, 1..=8192, Message::WidthChanged).step(1),
+                ),
+                setting(
+                    "Height",
+                    number_input(&settings.height, 1..=8192, Message::HeightChanged).step(1),
+                ),
+                setting(
+                    "Frames per second",
+                    number_input(&settings.fps, 1..=240, Message::FpsChanged).step(1),
+                ),
+                setting(
+                    "Frames",
+                    number_input(&settings.frames, 1..=10000, Message::FramesChanged).step(1),
+                ),
+                setting(
+                    "Start time",
+                    number_input(&settings.start, 0.0..=3600.0, Message::StartChanged).step(0.1),
+                ),
+                setting(
+                    "Format",
+                    pick_list(Format::ALL, Some(settings.format), Message::FormatSelected),
+                ),
+            ],
+            &self.state,
+            format!("Rendering {} frames...", settings.frames),
+            Message::Start,
+        )
+    }
+}
+
+/// The settings of an export dialog above the status of its export and the button starting it.
+pub fn dialog<'a, Message: Clone + 'a>(
+    settings: impl IntoIterator<Item = Element<'a, Message>>,
+    state: &State,
+    exporting: String,
+    start: Message,
+) -> Element<'a, Message> {
+    let status = match state {
+        State::Idle => String::new(),
+        State::Exporting => exporting,
+        State::Finished(Ok(path)) => format!("Exported to {}", path.display()),
+        State::Finished(Err(error)) => error.to_string(),
+    };
+
+    column(settings)
+        .push(
+            row![
+                text(status),
+                horizontal_space(),
+                button("Export").on_press_maybe((!state.is_exporting()).then_some(start)),
+            ]
+            .align_y(Center),
+        )
+        .spacing(5)
+        .width(360.0)
+        .padding(10)
+        .into()
+}
+
+pub fn setting<'a, Message: 'a>(
+    label: &'a str,
+    input: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    row![text(label), horizontal_space(), input.into()]
+        .align_y(Center)
+        .into()
+}
+
+/// Writes the export to a file picked by the user.
+///
+/// Rendering and encoding block, so the writing runs on a blocking thread off the executor.
+pub async fn export(
+    file_name: &str,
+    write: impl FnOnce(&Path) -> Result<(), Error> + Send + 'static,
+) -> Result<PathBuf, Error> {
+    let path = util::pick_save_path(file_name).await?;
+
+    tokio::task::spawn_blocking(move || {
+        write(&path)?;
+        Ok(path)
+    })
+    .await?
+}
+
+async fn render(settings: Settings, updates: Vec<ShaderUpdate>, path: &Path) -> Result<(), Error> {
+    let mut renderer =
+        headless::Renderer::new(updates, Size::new(settings.width, settings.height)).await?;
+    let mut output = Output::create(&settings, renderer.size(), path)?;
+
+    renderer.seek(Duration::from_secs_f32(settings.start));
+    let step = Duration::from_secs_f64(1.0 / f64::from(settings.fps));
+
+    for index in 0..settings.frames {
+        output.write(index, renderer.render()?)?;
+        renderer.advance(step);
+    }
+
+    output.finish()
+}
+
+enum Output {
+    /// Frames are written next to the picked path, numbered after its file stem.
+    Sequence(PathBuf),
+    Gif(GifEncoder<BufWriter<File>>, image::Delay),
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl Output {
+    fn create(settings: &Settings, size: Size<u32>, path: &Path) -> Result<Self, Error> {
+        Ok(match settings.format {
+            Format::PngSequence => Output::Sequence(path.to_owned()),
+            Format::Gif => {
+                let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+                encoder.set_repeat(Repeat::Infinite)?;
+                // GIF delays have a resolution of 10ms, the encoder rounds to it
+                let delay = image::Delay::from_numer_denom_ms(1000, settings.fps);
+                Output::Gif(encoder, delay)
+            }
+            Format::Apng => {
+                let mut encoder =
+                    png::Encoder::new(BufWriter::new(File::create(path)?), size.width, size.height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(settings.frames, 0)?;
+                encoder.set_frame_delay(1, settings.fps.try_into().unwrap_or(u16::MAX))?;
+                Output::Apng(encoder.write_header()?)
+            }
+        })
+    }
+
+    fn write(&mut self, index: u32, image: image::RgbaImage) -> Result<(), Error> {
+        match self {
+            Output::Sequence(path) => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                image.save(path.with_file_name(format!("{stem}_{index:04}.png")))?;
+            }
+            Output::Gif(encoder, delay) => {
+                encoder.encode_frame(image::Frame::from_parts(image, 0, 0, *delay))?;
+            }
+            Output::Apng(writer) => writer.write_image_data(&image)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self {
+            Output::Sequence(_) => {}
+            Output::Gif(encoder, _) => drop(encoder),
+            Output::Apng(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use iced::widget::{button, column, horizontal_space, row, scrollable, text, text_editor};
+use iced::{Element, Font, Task};
+
+use crate::shader_update::Language;
+use crate::util::{self, FileName};
+use crate::viewer::shadertoy::{self, Imported};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ActionPerformed(text_editor::Action),
+    OpenFile,
+    FileOpened(Result<(FileName, Arc<String>), util::Error>),
+    /// Handled by the application, which passes the project's language and whether the image pass
+    /// has code to [`GlslImport::import`].
+    Import,
+    /// Replaces the code of the image pass with the translation waiting for confirmation.
+    Replace,
+    CancelReplace,
+    /// Handled by the application, which replaces the image pass with the translated shader.
+    Imported(Imported),
+}
+
+/// Translates a Shadertoy shader, pasted or opened from a file, to the project's language.
+#[derive(Default)]
+pub struct GlslImport {
+    source: text_editor::Content,
+    /// The replaced features of the last successful import, or the errors of a failed one.
+    report: Option<Result<Vec<String>, Vec<String>>>,
+    /// A translation that replaces code in the image pass once confirmed.
+    pending: Option<Imported>,
+    is_loading: bool,
+}
+
+impl GlslImport {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ActionPerformed(action) => {
+                if action.is_edit() {
+                    self.pending = None;
+                }
+                self.source.perform(action);
+                Task::none()
+            }
+            Message::OpenFile => {
+                if self.is_loading {
+                    Task::none()
+                } else {
+                    self.is_loading = true;
+                    Task::perform(util::open_file(), Message::FileOpened)
+                }
+            }
+            Message::FileOpened(result) => {
+                self.is_loading = false;
+                if let Ok((_, contents)) = result {
+                    self.source = text_editor::Content::with_text(&contents);
+                    self.report = None;
+                    self.pending = None;
+                }
+                Task::none()
+            }
+            Message::Replace => match self.pending.take() {
+                Some(imported) => Task::done(Message::Imported(imported)),
+                None => Task::none(),
+            },
+            Message::CancelReplace => {
+                self.pending = None;
+                self.report = None;
+                Task::none()
+            }
+            Message::Import | Message::Imported(_) => Task::none(),
+        }
+    }
+
+    /// Translates the shader, asking before it replaces the code of the image pass.
+    pub fn import(&mut self, language: Language, is_image_empty: bool) -> Task<Message> {
+        match shadertoy::import(&self.source.text(), language) {
+            Ok(imported) => {
+                self.report = Some(Ok(imported.unsupported.clone()));
+                if is_image_empty {
+                    Task::done(Message::Imported(imported))
+                } else {
+                    self.pending = Some(imported);
+                    Task::none()
+                }
+            }
+            Err(errors) => {
+                self.report = Some(Err(errors));
+                Task::none()
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let report: Element<_> = match &self.report {
+            Some(Ok(_)) if self.pending.is_some() => {
+                text("The image pass has code, replacing it can't be undone").into()
+            }
+            None => text("Paste a Shadertoy shader with a `mainImage` function").into(),
+            Some(Ok(unsupported)) if unsupported.is_empty() => {
+                text("Imported into the image pass").into()
+            }
+            Some(Ok(unsupported)) => column![
+                text("Imported into the image pass, with unsupported features replaced:"),
+                lines(unsupported),
+            ]
+            .into(),
+            Some(Err(errors)) => {
+                column![text("The shader could not be translated:"), lines(errors)].into()
+            }
+        };
+
+        column![
+            text_editor(&self.source)
+                .on_action(Message::ActionPerformed)
+                .font(Font::MONOSPACE)
+                .height(320.0),
+            row![
+                button("Open file").on_press_maybe((!self.is_loading).then_some(Message::OpenFile)),
+                horizontal_space(),
+            ]
+            .push(if self.pending.is_some() {
+                row![
+                    button("Cancel")
+                        .style(button::secondary)
+                        .on_press(Message::CancelReplace),
+                    button("Replace image pass")
+                        .style(button::danger)
+                        .on_press(Message::Replace),
+                ]
+                .spacing(5)
+            } else {
+                row![button("Import").on_press(Message::Import)]
+            }),
+            report,
+        ]
+        .spacing(10)
+        .width(640.0)
+        .padding(10)
+        .into()
+    }
+}
+
+fn lines(lines: &[String]) -> Element<'_, Message> {
+    scrollable(column(lines.iter().map(|line| text(line).into())))
+        .height(120.0)
+        .into()
+}
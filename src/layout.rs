@@ -24,7 +24,12 @@ impl Layout {
                 axis: pane_grid::Axis::Vertical,
                 ratio: 0.5,
                 a: Box::new(Configuration::Pane(PaneContent::Editor)),
-                b: Box::new(Configuration::Pane(PaneContent::Viewer)),
+                b: Box::new(Configuration::Split {
+                    axis: pane_grid::Axis::Horizontal,
+                    ratio: 0.8,
+                    a: Box::new(Configuration::Pane(PaneContent::Viewer)),
+                    b: Box::new(Configuration::Pane(PaneContent::Diagnostics)),
+                }),
             }),
             focus: None,
         }
@@ -72,4 +77,5 @@ impl Layout {
 pub enum PaneContent {
     Editor,
     Viewer,
+    Diagnostics,
 }
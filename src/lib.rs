@@ -1,20 +1,35 @@
+mod audio;
+mod channel;
+mod channels_editor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+mod diagnostics;
 mod editor;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+mod glsl_import;
 mod layout;
 mod menu;
 mod shader_update;
+#[cfg(not(target_arch = "wasm32"))]
+mod sound_export;
 mod text_editor;
 mod uniforms_editor;
 mod util;
 mod viewer;
 
 use iced::keyboard::key;
-use iced::widget::{button, center, column, container, mouse_area, opaque, stack, text};
-use iced::{keyboard, Color, Element, Event, Font, Length, Subscription, Task, Theme};
+use iced::widget::{
+    button, center, column, container, mouse_area, opaque, scrollable, stack, text,
+};
+use iced::{event, keyboard, Color, Element, Event, Font, Length, Subscription, Task, Theme};
 use util::Error;
 
 use std::sync::Arc;
 
+use crate::diagnostics::Diagnostic;
 use crate::util::FileName;
+use crate::viewer::{portability, transport};
 
 pub struct Application {
     editor: editor::Editor,
@@ -23,6 +38,18 @@ pub struct Application {
     file: Option<FileName>,
     is_loading: bool,
     show_menu: bool,
+    /// The result of the last Shadertoy export while it is shown.
+    shadertoy: Option<Result<String, Vec<Diagnostic>>>,
+    /// The result of the last portability check while it is shown.
+    portability: Option<Result<Vec<portability::Report>, Vec<Diagnostic>>>,
+    glsl_import: glsl_import::GlslImport,
+    show_glsl_import: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export: export::Export,
+    show_export: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    sound_export: sound_export::SoundExport,
+    show_sound_export: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -30,9 +57,28 @@ pub enum Message {
     Editor(editor::Message),
     Viewer(viewer::Message),
     Layout(layout::Message),
+    Diagnostics(diagnostics::Message),
     ShowMenu,
     CloseMenu,
     Menu(menu::Message),
+    #[cfg(not(target_arch = "wasm32"))]
+    ShowExport,
+    CloseExport,
+    #[cfg(not(target_arch = "wasm32"))]
+    ShowSoundExport,
+    CloseSoundExport,
+    ExportShadertoy,
+    CloseShadertoy,
+    CheckPortability,
+    PortabilityChecked(Result<Vec<portability::Report>, Vec<Diagnostic>>),
+    ClosePortability,
+    ShowGlslImport,
+    CloseGlslImport,
+    GlslImport(glsl_import::Message),
+    #[cfg(not(target_arch = "wasm32"))]
+    Export(export::Message),
+    #[cfg(not(target_arch = "wasm32"))]
+    SoundExport(sound_export::Message),
     OpenProject,
     NewProject,
     ProjectOpened(Result<(FileName, Arc<String>), Error>),
@@ -51,6 +97,16 @@ impl Application {
             file: None,
             is_loading: false,
             show_menu: false,
+            shadertoy: None,
+            portability: None,
+            glsl_import: glsl_import::GlslImport::default(),
+            show_glsl_import: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export: export::Export::default(),
+            show_export: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            sound_export: sound_export::SoundExport::default(),
+            show_sound_export: false,
         }
     }
 
@@ -64,6 +120,11 @@ impl Application {
             },
             Message::Viewer(message) => self.viewer.update(message).map(Message::Viewer),
             Message::Layout(message) => self.layout.update(message).map(Message::Layout),
+            Message::Diagnostics(diagnostics::Message::Selected(pass, location)) => {
+                self.shadertoy = None;
+                self.portability = None;
+                Task::done(Message::Editor(editor::Message::GoTo(pass, location)))
+            }
             Message::ShowMenu => {
                 self.show_menu = true;
                 Task::none()
@@ -77,8 +138,108 @@ impl Application {
                 menu::Message::NewProject => Task::done(Message::NewProject),
                 menu::Message::SaveProject => Task::done(Message::SaveProject),
                 menu::Message::SaveProjectAs => Task::done(Message::SaveProjectAs),
+                menu::Message::ImportGlsl => {
+                    self.show_menu = false;
+                    Task::done(Message::ShowGlslImport)
+                }
+                menu::Message::ExportShadertoy => {
+                    self.show_menu = false;
+                    Task::done(Message::ExportShadertoy)
+                }
+                menu::Message::CheckPortability => {
+                    self.show_menu = false;
+                    Task::done(Message::CheckPortability)
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                menu::Message::ExportAnimation => {
+                    self.show_menu = false;
+                    Task::done(Message::ShowExport)
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                menu::Message::ExportSound => {
+                    self.show_menu = false;
+                    Task::done(Message::ShowSoundExport)
+                }
                 menu::Message::Editor(message) => Task::done(Message::Editor(message)),
             },
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::ShowExport => {
+                self.show_export = true;
+                Task::none()
+            }
+            Message::CloseExport => {
+                self.show_export = false;
+                Task::none()
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::Export(message) => match message {
+                export::Message::Start => {
+                    // Renders with the timeline, output and camera the viewer has now
+                    self.export
+                        .start(self.editor.updates_with(&self.snapshot()))
+                        .map(Message::Export)
+                }
+                _ => self.export.update(message).map(Message::Export),
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::ShowSoundExport => {
+                self.show_sound_export = true;
+                Task::none()
+            }
+            Message::CloseSoundExport => {
+                self.show_sound_export = false;
+                Task::none()
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::SoundExport(message) => match message {
+                sound_export::Message::Start => self
+                    .sound_export
+                    .start(self.editor.updates_with(&self.snapshot()))
+                    .map(Message::SoundExport),
+                _ => self.sound_export.update(message).map(Message::SoundExport),
+            },
+            Message::ExportShadertoy => {
+                let result = self.editor.shadertoy(&self.snapshot().camera);
+                let task = match &result {
+                    Ok(code) => iced::clipboard::write(code.clone()),
+                    Err(_) => Task::none(),
+                };
+                self.shadertoy = Some(result);
+                task
+            }
+            Message::CloseShadertoy => {
+                self.shadertoy = None;
+                Task::none()
+            }
+            Message::CheckPortability => {
+                Task::perform(self.viewer.check_portability(), Message::PortabilityChecked)
+            }
+            Message::PortabilityChecked(result) => {
+                self.portability = Some(result);
+                Task::none()
+            }
+            Message::ClosePortability => {
+                self.portability = None;
+                Task::none()
+            }
+            Message::ShowGlslImport => {
+                self.show_glsl_import = true;
+                Task::none()
+            }
+            Message::CloseGlslImport => {
+                self.show_glsl_import = false;
+                Task::none()
+            }
+            Message::GlslImport(message) => match message {
+                glsl_import::Message::Import => self
+                    .glsl_import
+                    .import(self.editor.language(), self.editor.is_image_empty())
+                    .map(Message::GlslImport),
+                glsl_import::Message::Imported(imported) => {
+                    Task::done(Message::Editor(editor::Message::Import(imported)))
+                }
+                _ => self.glsl_import.update(message).map(Message::GlslImport),
+            },
             Message::OpenProject => {
                 if self.is_loading {
                     Task::none()
@@ -102,7 +263,7 @@ impl Application {
                 self.is_loading = false;
 
                 if let Ok((path, contents)) = result {
-                    if let Ok(editor) = serde_json::from_str(&contents) {
+                    if let Ok(editor) = editor::Editor::open(&contents) {
                         self.file = Some(path);
                         self.editor = editor;
                         self.editor
@@ -116,6 +277,8 @@ impl Application {
                 }
             }
             Message::SaveProject => {
+                self.editor.set_snapshot(self.snapshot());
+
                 if self.is_loading {
                     Task::none()
                 } else if let Ok(content) = serde_json::to_string(&self.editor) {
@@ -129,6 +292,8 @@ impl Application {
                 }
             }
             Message::SaveProjectAs => {
+                self.editor.set_snapshot(self.snapshot());
+
                 if self.is_loading {
                     Task::none()
                 } else if let Ok(content) = serde_json::to_string(&self.editor) {
@@ -151,7 +316,22 @@ impl Application {
                 Event::Keyboard(keyboard::Event::KeyPressed {
                     key: keyboard::Key::Named(key::Named::Escape),
                     ..
-                }) => Task::done(Message::CloseMenu),
+                }) => Task::batch([
+                    Task::done(Message::CloseMenu),
+                    Task::done(Message::CloseExport),
+                    Task::done(Message::CloseSoundExport),
+                    Task::done(Message::CloseShadertoy),
+                    Task::done(Message::ClosePortability),
+                    Task::done(Message::CloseGlslImport),
+                ]),
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    match transport::shortcut(&key, modifiers) {
+                        Some(message) => {
+                            Task::done(Message::Viewer(viewer::Message::Transport(message)))
+                        }
+                        None => Task::none(),
+                    }
+                }
                 _ => Task::none(),
             },
         }
@@ -168,9 +348,16 @@ impl Application {
                         .filename_display_text()
                         .or(Some(String::from("New file"))),
                 ),
-                layout::PaneContent::Viewer => {
-                    (self.viewer.view().map(Message::Viewer).into(), None)
-                }
+                layout::PaneContent::Viewer => (
+                    self.viewer.view().map(Message::Viewer).into(),
+                    Some(self.viewer.title()),
+                ),
+                layout::PaneContent::Diagnostics => (
+                    diagnostics::view(self.viewer.diagnostics())
+                        .map(Message::Diagnostics)
+                        .into(),
+                    Some(String::from("Diagnostics")),
+                ),
             })
             .on_click(|e| Message::Layout(layout::Message::Clicked(e)))
             .on_drag(|e| Message::Layout(layout::Message::Dragged(e)))
@@ -184,17 +371,77 @@ impl Application {
 
         if self.show_menu {
             modal(content, menu::view().map(Message::Menu), Message::CloseMenu)
+        } else if let Some(result) = &self.shadertoy {
+            modal(content, shadertoy_view(result), Message::CloseShadertoy)
+        } else if let Some(result) = &self.portability {
+            modal(content, portability_view(result), Message::ClosePortability)
+        } else if self.show_glsl_import {
+            modal(
+                content,
+                container(self.glsl_import.view().map(Message::GlslImport))
+                    .style(container::rounded_box),
+                Message::CloseGlslImport,
+            )
+        } else if self.show_export {
+            self.export_view(content)
+        } else if self.show_sound_export {
+            self.sound_export_view(content)
         } else {
             content
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_view<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        modal(
+            content,
+            container(self.export.view().map(Message::Export)).style(container::rounded_box),
+            Message::CloseExport,
+        )
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export_view<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        content
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sound_export_view<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        modal(
+            content,
+            container(self.sound_export.view().map(Message::SoundExport))
+                .style(container::rounded_box),
+            Message::CloseSoundExport,
+        )
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn sound_export_view<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        content
+    }
+
     pub fn theme(&self) -> Theme {
         self.editor.text().theme()
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
-        self.editor.subscription().map(Message::Editor)
+        Subscription::batch([
+            self.viewer.subscription().map(Message::Viewer),
+            // Keys typed into the text editor are captured by it and don't trigger shortcuts
+            event::listen_with(|event, status, _window| match status {
+                event::Status::Ignored => Some(Message::Event(event)),
+                event::Status::Captured => None,
+            }),
+        ])
+    }
+
+    /// The state of the viewer that projects are saved with and exports render from.
+    fn snapshot(&self) -> editor::Snapshot {
+        editor::Snapshot {
+            transport: self.viewer.transport().clone(),
+            output: self.viewer.output(),
+            camera: self.viewer.camera(),
+        }
     }
 }
 
@@ -212,6 +459,67 @@ fn menu_icon<'a>() -> Element<'a, Message> {
         .into()
 }
 
+fn shadertoy_view(result: &Result<String, Vec<Diagnostic>>) -> Element<'_, Message> {
+    let content = match result {
+        Ok(code) => column![
+            text("Copied to the clipboard, paste it into the image tab of a new Shadertoy"),
+            scrollable(text(code).font(Font::MONOSPACE)),
+        ],
+        Err(diagnostics) => column![
+            text("The image pass could not be converted"),
+            diagnostics::view(diagnostics).map(Message::Diagnostics),
+        ],
+    };
+
+    container(content.spacing(10))
+        .width(640.0)
+        .max_height(480.0)
+        .padding(10)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn portability_view(
+    result: &Result<Vec<portability::Report>, Vec<Diagnostic>>,
+) -> Element<'_, Message> {
+    let content = match result {
+        Ok(reports) => column(reports.iter().map(|report| {
+            let summary = if report.errors.is_empty() && report.warnings.is_empty() {
+                String::from("passed")
+            } else {
+                format!(
+                    "{} errors, {} warnings",
+                    report.errors.len(),
+                    report.warnings.len()
+                )
+            };
+
+            let mut section = column![
+                text(format!("{}: {summary}", report.target)).size(18),
+                diagnostics::list(&report.errors).map(Message::Diagnostics),
+            ];
+            if !report.warnings.is_empty() {
+                section = section
+                    .push(text("Warnings"))
+                    .push(diagnostics::list(&report.warnings).map(Message::Diagnostics));
+            }
+            section.into()
+        }))
+        .spacing(10),
+        Err(diagnostics) => column![
+            text("The shaders have to compile before their portability can be checked"),
+            diagnostics::list(diagnostics).map(Message::Diagnostics),
+        ],
+    };
+
+    container(scrollable(content))
+        .width(640.0)
+        .max_height(480.0)
+        .padding(10)
+        .style(container::rounded_box)
+        .into()
+}
+
 fn modal<'a>(
     base: impl Into<Element<'a, Message>>,
     content: impl Into<Element<'a, Message>>,
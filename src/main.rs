@@ -8,7 +8,18 @@ fn main() -> iced::Result {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    tracing_subscriber::fmt::init();
+    {
+        tracing_subscriber::fmt::init();
+
+        let mut args = std::env::args().skip(1).peekable();
+        if args.next_if(|arg| arg == "render").is_some() {
+            if let Err(error) = bulin::cli::Render::parse(args).and_then(bulin::cli::Render::run) {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    }
 
     iced::application(Application::default, Application::update, Application::view)
         .subscription(Application::subscription)
@@ -14,11 +14,18 @@ pub enum Message {
     OpenProject,
     SaveProject,
     SaveProjectAs,
+    ExportShadertoy,
+    ImportGlsl,
+    CheckPortability,
+    #[cfg(not(target_arch = "wasm32"))]
+    ExportAnimation,
+    #[cfg(not(target_arch = "wasm32"))]
+    ExportSound,
     Editor(editor::Message),
 }
 
 pub fn view() -> Element<'static, Message> {
-    container(column![
+    let items = column![
         menu_item(
             "Open File",
             Message::Editor(editor::Message::TextEditor(text_editor::Message::OpenFile))
@@ -42,20 +49,33 @@ pub fn view() -> Element<'static, Message> {
         menu_item("New Project", Message::NewProject),
         menu_item("Save Project", Message::SaveProject),
         menu_item("Save Project as", Message::SaveProjectAs),
-        rule::Rule::horizontal(1),
-        menu_item(
-            "Undo",
-            Message::Editor(editor::Message::TextEditor(
-                text_editor::Message::ActionPerformed(Action::Undo,)
-            )),
-        ),
-        menu_item(
-            "Redo",
-            Message::Editor(editor::Message::TextEditor(
-                text_editor::Message::ActionPerformed(Action::Redo,)
+        menu_item("Import GLSL", Message::ImportGlsl),
+        menu_item("Export to Shadertoy", Message::ExportShadertoy),
+        menu_item("Check Portability", Message::CheckPortability),
+    ];
+
+    // Exporting renders offscreen on a device of its own, which the web build doesn't provide
+    #[cfg(not(target_arch = "wasm32"))]
+    let items = items
+        .push(menu_item("Export Animation", Message::ExportAnimation))
+        .push(menu_item("Export Sound", Message::ExportSound));
+
+    container(
+        items
+            .push(rule::Rule::horizontal(1))
+            .push(menu_item(
+                "Undo",
+                Message::Editor(editor::Message::TextEditor(
+                    text_editor::Message::ActionPerformed(Action::Undo),
+                )),
+            ))
+            .push(menu_item(
+                "Redo",
+                Message::Editor(editor::Message::TextEditor(
+                    text_editor::Message::ActionPerformed(Action::Redo),
+                )),
             )),
-        )
-    ])
+    )
     .width(180.0)
     .padding(10)
     .style(container::rounded_box)
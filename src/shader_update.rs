@@ -1,11 +1,92 @@
+use crate::channel::Channel;
 use crate::uniforms_editor::uniform::*;
+use crate::viewer::camera::Camera;
+use crate::viewer::output::Output;
+use crate::viewer::transport::Transport;
+
+use serde::{Deserialize, Serialize};
 
 pub type FragmentShader = String;
 
+/// A render pass of a project, ordered by when it is executed within a frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Pass {
+    BufferA,
+    BufferB,
+    BufferC,
+    BufferD,
+    /// The pass shown in the viewer.
+    #[default]
+    Image,
+}
+
+impl Pass {
+    pub const BUFFERS: [Pass; 4] = [Pass::BufferA, Pass::BufferB, Pass::BufferC, Pass::BufferD];
+    pub const ALL: [Pass; 5] = [
+        Pass::BufferA,
+        Pass::BufferB,
+        Pass::BufferC,
+        Pass::BufferD,
+        Pass::Image,
+    ];
+}
+
+impl std::fmt::Display for Pass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::BufferA => "Buffer A",
+            Self::BufferB => "Buffer B",
+            Self::BufferC => "Buffer C",
+            Self::BufferD => "Buffer D",
+            Self::Image => "Image",
+        })
+    }
+}
+
+/// The language the shaders of a project are written in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    Wgsl,
+    Glsl,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Wgsl, Language::Glsl];
+
+    /// The token selecting the syntax definition of the language.
+    pub fn token(&self) -> &'static str {
+        match self {
+            Self::Wgsl => "wgsl",
+            Self::Glsl => "glsl",
+        }
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Wgsl => "WGSL",
+            Self::Glsl => "GLSL",
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ShaderUpdate {
-    Shader(FragmentShader),
+    Shader(Pass, FragmentShader),
+    RemovePass(Pass),
     Uniforms(UniformsUpdate),
+    /// Binds the image to the channel with the index, or unbinds it.
+    Channel(usize, Option<Channel>),
+    /// Recompiles all passes in the language.
+    Language(Language),
+    /// Replaces the timeline of the viewer.
+    Transport(Transport),
+    /// Changes how the image pass is stored and shown.
+    Output(Output),
+    /// Moves the camera the shaders see.
+    Camera(Camera),
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use iced::widget::{pick_list, text};
+use iced::{Element, Task};
+use iced_aw::number_input;
+
+use crate::export::{self, dialog, setting, Error, State};
+use crate::shader_update::ShaderUpdate;
+use crate::viewer::sound;
+
+/// Sample rates offered for export, in samples per second.
+const SAMPLE_RATES: [u32; 4] = [22_050, 44_100, 48_000, 96_000];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    DurationChanged(f32),
+    SampleRateSelected(u32),
+    /// Handled by the application, which passes the project's updates to [`SoundExport::start`].
+    Start,
+    Exported(Result<PathBuf, Error>),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    /// Length of the sound in seconds.
+    duration: f32,
+    sample_rate: u32,
+}
+
+impl Settings {
+    fn samples(&self) -> u32 {
+        (self.duration * self.sample_rate as f32).round() as u32
+    }
+}
+
+/// Renders the `sound` function of the image pass to a stereo WAV file.
+pub struct SoundExport {
+    settings: Settings,
+    state: State,
+}
+
+impl Default for SoundExport {
+    fn default() -> Self {
+        Self {
+            settings: Settings {
+                duration: 10.0,
+                sample_rate: 44_100,
+            },
+            state: State::default(),
+        }
+    }
+}
+
+impl SoundExport {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let settings = &mut self.settings;
+        match message {
+            Message::DurationChanged(duration) => settings.duration = duration,
+            Message::SampleRateSelected(sample_rate) => settings.sample_rate = sample_rate,
+            Message::Start => {}
+            Message::Exported(result) => self.state.finish(result),
+        }
+        Task::none()
+    }
+
+    /// Renders the sound of the scene described by the updates and writes it to a file picked by
+    /// the user.
+    pub fn start(&mut self, updates: Vec<ShaderUpdate>) -> Task<Message> {
+        if !self.state.start() {
+            return Task::none();
+        }
+
+        let settings = self.settings;
+        Task::perform(
+            export::export("sound.wav", move |path| {
+                let samples = iced::futures::executor::block_on(sound::render(
+                    updates,
+                    settings.samples(),
+                    settings.sample_rate,
+                ))?;
+                Ok(write_wav(path, settings.sample_rate, &samples)?)
+            }),
+            Message::Exported,
+        )
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let settings = &self.settings;
+
+        dialog(
+            [
+                text("Renders `fn sound(time: f32) -> vec2<f32>` of the image pass")
+                    .size(14)
+                    .into(),
+                setting(
+                    "Duration in seconds",
+                    number_input(&settings.duration, 0.1..=600.0, Message::DurationChanged)
+                        .step(0.1),
+                ),
+                setting(
+                    "Sample rate",
+                    pick_list(
+                        SAMPLE_RATES,
+                        Some(settings.sample_rate),
+                        Message::SampleRateSelected,
+                    ),
+                ),
+            ],
+            &self.state,
+            format!("Rendering {} samples...", settings.samples()),
+            Message::Start,
+        )
+    }
+}
+
+/// Writes the samples as 16 bit PCM, clipping what's outside of -1 to 1.
+fn write_wav(path: &Path, sample_rate: u32, samples: &[[f32; 2]]) -> io::Result<()> {
+    const CHANNELS: u16 = 2;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    let data_size = u32::try_from(samples.len() * usize::from(BLOCK_ALIGN))
+        .map_err(|_| io::Error::other("Sound is too long for a WAV file"))?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // Integer PCM
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * u32::from(BLOCK_ALIGN)).to_le_bytes())?;
+    writer.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for channel in samples.iter().flatten() {
+        // Saturates, with NaN becoming silence
+        let value = (channel.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_wav_header_and_samples() {
+        let path = std::env::temp_dir().join(format!("sound_export_{}.wav", std::process::id()));
+        let samples = [[0.0, 1.0], [-1.0, 0.5], [2.0, f32::NAN]];
+        write_wav(&path, 48_000, &samples).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        assert_eq!(bytes.len(), 44 + 12);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(4), 36 + 12);
+        assert_eq!(&bytes[8..12], b"WAVE");
+
+        assert_eq!(&bytes[12..16], b"fmt ");
+        assert_eq!(u32_at(16), 16);
+        assert_eq!(u16_at(20), 1);
+        assert_eq!(u16_at(22), 2);
+        assert_eq!(u32_at(24), 48_000);
+        assert_eq!(u32_at(28), 48_000 * 4);
+        assert_eq!(u16_at(32), 4);
+        assert_eq!(u16_at(34), 16);
+
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(40), 12);
+        let values: Vec<i16> = bytes[44..]
+            .chunks_exact(2)
+            .map(|value| i16::from_le_bytes([value[0], value[1]]))
+            .collect();
+        assert_eq!(values, [0, i16::MAX, -i16::MAX, 16384, i16::MAX, 0]);
+    }
+}
@@ -1,9 +1,10 @@
 mod content;
 use content::Content;
-mod wgsl_highlighter;
-use wgsl_highlighter::WGSLHighlighter;
+mod shader_highlighter;
+use shader_highlighter::ShaderHighlighter;
 
-use crate::shader_update::FragmentShader;
+use crate::diagnostics::Location;
+use crate::shader_update::{FragmentShader, Language};
 use crate::util::{self, FileName};
 
 use iced::keyboard;
@@ -51,6 +52,7 @@ pub enum Message {
     SaveFile,
     SaveFileAs,
     FileSaved(Result<FileName, util::Error>),
+    GoTo(Location),
     UpdatePipeline(FragmentShader),
 }
 
@@ -149,11 +151,37 @@ impl TextEditor {
 
                 Task::none()
             }
+            Message::GoTo(location) => {
+                let text = self.content.text();
+
+                let start: usize = text
+                    .split_inclusive('\n')
+                    .take(location.line - 1)
+                    .map(str::len)
+                    .sum();
+                let line = text[start..].lines().next().unwrap_or_default();
+                let column = line
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .find(|i| *i >= location.column - 1)
+                    .unwrap_or(line.len());
+
+                // Pasting the text in front of the location leaves the cursor there in one step,
+                // where moving it would take a motion per character or per wrapped line
+                let (before, after) = text.split_at(start + column);
+                self.content = Content::with_text(after);
+                self.content
+                    .perform(text_editor::Action::Edit(text_editor::Edit::Paste(
+                        Arc::new(before.to_string()),
+                    )));
+
+                Task::none()
+            }
             Message::UpdatePipeline(_) => Task::none(),
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self, language: Language) -> Element<Message> {
         let controls = row![
             action(new_icon(), "New file", Some(Message::NewFile)),
             action(
@@ -187,10 +215,10 @@ impl TextEditor {
                 } else {
                     text::Wrapping::None
                 })
-                .highlight_with::<WGSLHighlighter>(
+                .highlight_with::<ShaderHighlighter>(
                     Settings {
                         theme: self.theme,
-                        token: "wgsl".to_string(),
+                        token: language.token().to_string(),
                     },
                     |highlight, _theme| highlight.to_format(),
                 )
@@ -251,6 +279,11 @@ impl TextEditor {
     pub fn content(&self) -> String {
         self.content.text()
     }
+
+    /// Replaces every occurrence of the identifier `from` in the content with `to`.
+    pub fn replace(&mut self, from: &str, to: &str) {
+        self.content = Content::with_text(&util::replace_identifier(&self.content(), from, to));
+    }
 }
 
 fn action<'a, Message: Clone + 'a>(
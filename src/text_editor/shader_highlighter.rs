@@ -1,4 +1,4 @@
-//! A WGSL syntax highlighter for iced.
+//! A WGSL and GLSL syntax highlighter for iced.
 use iced::advanced::text;
 use iced_highlighter::Highlighter;
 use iced_highlighter::Theme;
@@ -7,42 +7,40 @@ use once_cell::sync::Lazy;
 use syntect::highlighting;
 use syntect::parsing;
 
-static WGSL_SYNTAX_SET: Lazy<parsing::SyntaxSet> = Lazy::new(|| {
+static SYNTAX_SET: Lazy<parsing::SyntaxSet> = Lazy::new(|| {
     let mut builder = parsing::SyntaxSetBuilder::new();
-    builder.add(
-        parsing::SyntaxDefinition::load_from_str(
-            include_str!("../../assets/WGSL.sublime-syntax"),
-            true,
-            None,
-        )
-        .unwrap(),
-    );
+    for syntax in [
+        include_str!("../../assets/WGSL.sublime-syntax"),
+        include_str!("../../assets/GLSL.sublime-syntax"),
+    ] {
+        builder.add(parsing::SyntaxDefinition::load_from_str(syntax, true, None).unwrap());
+    }
     builder.build()
 });
 static THEMES: Lazy<highlighting::ThemeSet> = Lazy::new(highlighting::ThemeSet::load_defaults);
 
-/// A syntax highlighter.
+/// A syntax highlighter, selecting the language by the token of its settings.
 #[derive(Debug)]
-pub struct WGSLHighlighter(Highlighter);
+pub struct ShaderHighlighter(Highlighter);
 
-impl text::Highlighter for WGSLHighlighter {
+impl text::Highlighter for ShaderHighlighter {
     type Settings = <Highlighter as text::Highlighter>::Settings;
     type Highlight = <Highlighter as text::Highlighter>::Highlight;
 
     type Iterator<'a> = <Highlighter as text::Highlighter>::Iterator<'a>;
 
     fn new(settings: &Self::Settings) -> Self {
-        let syntax = WGSL_SYNTAX_SET
+        let syntax = SYNTAX_SET
             .find_syntax_by_token(&settings.token)
-            .unwrap_or_else(|| WGSL_SYNTAX_SET.find_syntax_plain_text());
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
 
         let highlighter = highlighting::Highlighter::new(&THEMES.themes[key(settings.theme)]);
         let parser = parsing::ParseState::new(syntax);
         let stack = parsing::ScopeStack::new();
 
-        WGSLHighlighter(Highlighter::new(
+        ShaderHighlighter(Highlighter::new(
             syntax,
-            &WGSL_SYNTAX_SET,
+            &SYNTAX_SET,
             highlighter,
             vec![(parser, stack)],
             0,
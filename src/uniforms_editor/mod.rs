@@ -1,24 +1,20 @@
-mod time;
 pub mod uniform;
 
 use crate::shader_update::*;
 use uniform::*;
 
 use iced::{
-    widget::{button, horizontal_space, row},
-    Element, Subscription, Task,
+    widget::{button, row},
+    Element, Task,
 };
 use serde::{Deserialize, Serialize};
 use std::vec::Vec;
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    Time(time::Message),
     Uniforms(String, uniform::Message),
     Candidate(uniform::CandidateMessage),
     Update(ShaderUpdate),
-    AddTime,
-    RemoveTime,
     AddUniform(EditorUniform),
     RemoveUniform(String),
 }
@@ -28,7 +24,6 @@ pub struct UniformsEditor {
     uniforms: Vec<EditorUniform>,
     #[serde(skip, default = "Candidate::new")]
     candidate: Candidate,
-    time: Option<time::Time>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -42,43 +37,11 @@ impl UniformsEditor {
         Self {
             uniforms: Vec::new(),
             candidate: Candidate::new(),
-            time: Option::None,
         }
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::AddTime => {
-                self.time = Some(time::Time::new());
-                Task::done(Message::AddUniform(EditorUniform {
-                    value: Uniform {
-                        value: Type::Float(0.0),
-                        name: String::from("time"),
-                    },
-                    visible: false,
-                }))
-            }
-            Message::RemoveTime => {
-                self.time = Option::None;
-                Task::done(Message::RemoveUniform("time".to_string()))
-            }
-            Message::Time(message) => {
-                if let Some(time) = &mut self.time {
-                    time.update(message)
-                        .map(Message::Time)
-                        .chain(Task::done(Message::Update(ShaderUpdate::Uniforms(
-                            UniformsUpdate::Update(
-                                "time".to_string(),
-                                Uniform {
-                                    value: Type::Float(time.duration()),
-                                    name: "time".to_string(),
-                                },
-                            ),
-                        ))))
-                } else {
-                    Task::none()
-                }
-            }
             Message::AddUniform(uniform) => {
                 self.uniforms.push(uniform.clone());
                 Task::done(Message::Update(ShaderUpdate::Uniforms(
@@ -115,18 +78,6 @@ impl UniformsEditor {
     }
 
     pub fn view(&self) -> Element<Message> {
-        let time = if let Some(time) = &self.time {
-            row![
-                time.view().map(Message::Time),
-                button("X").on_press(Message::RemoveTime)
-            ]
-        } else {
-            row![
-                horizontal_space(),
-                button("Add time").on_press(Message::AddTime)
-            ]
-        };
-
         let candidate = row![
             self.candidate.view().map(Message::Candidate),
             if let Ok(uniform) = self.candidate.clone().try_into() {
@@ -154,18 +105,19 @@ impl UniformsEditor {
                 None
             }
         }));
-        iced::widget::column![time, candidate, uniforms].into()
+        iced::widget::column![candidate, uniforms].into()
     }
 
-    pub fn uniforms(&self) -> Vec<Uniform> {
-        self.uniforms.iter().map(|u| u.value.clone()).collect()
+    /// Removes the hidden `time` uniform that projects used before time became a built-in
+    /// uniform, returning whether there was one.
+    pub fn remove_legacy_time(&mut self) -> bool {
+        let count = self.uniforms.len();
+        self.uniforms
+            .retain(|uniform| uniform.visible || uniform.value.name != "time");
+        self.uniforms.len() != count
     }
 
-    pub fn subscription(&self) -> Subscription<Message> {
-        if let Some(time) = &self.time {
-            time.subscription().map(Message::Time)
-        } else {
-            Subscription::none()
-        }
+    pub fn uniforms(&self) -> Vec<Uniform> {
+        self.uniforms.iter().map(|u| u.value.clone()).collect()
     }
 }
@@ -56,6 +56,46 @@ impl Type {
             Self::VecInt4(_) => String::from("vec4<i32>"),
         }
     }
+
+    pub fn to_glsl_type(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::VecFloat2(_) => "vec2",
+            Self::VecFloat3(_) | Self::Col3(_) => "vec3",
+            Self::VecFloat4(_) | Self::Col4(_) => "vec4",
+            Self::VecInt2(_) => "ivec2",
+            Self::VecInt3(_) => "ivec3",
+            Self::VecInt4(_) => "ivec4",
+        }
+    }
+
+    /// Alignment and size in bytes of the type inside a WGSL uniform buffer.
+    pub fn layout(&self) -> (usize, usize) {
+        match self {
+            Self::Int(_) | Self::Float(_) => (4, 4),
+            Self::VecFloat2(_) | Self::VecInt2(_) => (8, 8),
+            Self::VecFloat3(_) | Self::Col3(_) | Self::VecInt3(_) => (16, 12),
+            Self::VecFloat4(_) | Self::Col4(_) | Self::VecInt4(_) => (16, 16),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            Self::Int(v) => bytemuck::bytes_of(&v).to_vec(),
+            Self::Float(v) => bytemuck::bytes_of(&v).to_vec(),
+            Self::VecFloat2((x, y)) => bytemuck::cast_slice(&[x, y]).to_vec(),
+            Self::VecFloat3((x, y, z)) | Self::Col3((x, y, z)) => {
+                bytemuck::cast_slice(&[x, y, z]).to_vec()
+            }
+            Self::VecFloat4((x, y, z, w)) | Self::Col4((x, y, z, w)) => {
+                bytemuck::cast_slice(&[x, y, z, w]).to_vec()
+            }
+            Self::VecInt2((x, y)) => bytemuck::cast_slice(&[x, y]).to_vec(),
+            Self::VecInt3((x, y, z)) => bytemuck::cast_slice(&[x, y, z]).to_vec(),
+            Self::VecInt4((x, y, z, w)) => bytemuck::cast_slice(&[x, y, z, w]).to_vec(),
+        }
+    }
 }
 
 impl std::fmt::Display for Type {
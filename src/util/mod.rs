@@ -10,6 +10,26 @@ pub enum Error {
     ReadFile(#[from] FromUtf8Error),
     #[error("I/O error occurred")]
     Io(#[source] Arc<io::Error>),
+    #[error("Background work failed: {0}")]
+    Blocking(String),
+}
+
+/// Replaces whole identifiers only, leaving ones that merely contain `from` untouched.
+pub fn replace_identifier(text: &str, from: &str, to: &str) -> String {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(from) {
+        let (before, after) = (&rest[..index], &rest[index + from.len()..]);
+        let is_whole = !before.ends_with(is_identifier) && !after.starts_with(is_identifier);
+
+        result.push_str(before);
+        result.push_str(if is_whole { to } else { from });
+        rest = after;
+    }
+    result.push_str(rest);
+    result
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
@@ -30,6 +31,27 @@ pub async fn open_file() -> Result<(FileName, Arc<String>), Error> {
         .map_err(Error::Io)
 }
 
+pub async fn open_binary(
+    title: &str,
+    filter: &str,
+    extensions: &[&str],
+) -> Result<(FileName, Arc<Vec<u8>>), Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title(title)
+        .add_filter(filter, extensions)
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    let path = picked_file.into();
+
+    tokio::fs::read(&path)
+        .await
+        .map(|contents| (FileName(path), Arc::new(contents)))
+        .map_err(Arc::new)
+        .map_err(Error::Io)
+}
+
 pub async fn save_file(path: Option<FileName>, contents: String) -> Result<FileName, Error> {
     let path = if let Some(path) = path {
         path.0
@@ -49,3 +71,27 @@ pub async fn save_file(path: Option<FileName>, contents: String) -> Result<FileN
         .map_err(Arc::new)
         .map_err(Error::Io)
 }
+
+pub async fn pick_save_path(file_name: &str) -> Result<PathBuf, Error> {
+    rfd::AsyncFileDialog::new()
+        .set_file_name(file_name)
+        .save_file()
+        .await
+        .as_ref()
+        .map(rfd::FileHandle::path)
+        .map(Path::to_owned)
+        .ok_or(Error::DialogClosed)
+}
+
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Runs blocking work on a thread of its own, so it doesn't stall the executor.
+pub async fn spawn_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, Error> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|error| Error::Blocking(error.to_string()))
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
@@ -25,6 +26,24 @@ pub async fn open_file() -> Result<(FileName, Arc<String>), Error> {
         .map_err(Error::ReadFile)
 }
 
+pub async fn open_binary(
+    title: &str,
+    filter: &str,
+    extensions: &[&str],
+) -> Result<(FileName, Arc<Vec<u8>>), Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title(title)
+        .add_filter(filter, extensions)
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    Ok((
+        FileName(picked_file.file_name()),
+        Arc::new(picked_file.read().await),
+    ))
+}
+
 pub async fn save_file(filename: Option<FileName>, contents: String) -> Result<FileName, Error> {
     let fallback = "bulin.wgsl".to_string();
     let filename = filename.map_or(fallback.clone(), |f| f.as_str().unwrap_or(&fallback).into());
@@ -42,3 +61,15 @@ pub async fn save_file(filename: Option<FileName>, contents: String) -> Result<F
         .map_err(Arc::new)
         .map_err(Error::Io)
 }
+
+pub async fn sleep(duration: Duration) {
+    wasmtimer::tokio::sleep(duration).await
+}
+
+/// Runs blocking work after yielding to the event loop once, so the view can show that it started.
+///
+/// The browser offers no threads to move it to, the work itself still blocks the page.
+pub async fn spawn_blocking<T>(work: impl FnOnce() -> T) -> Result<T, Error> {
+    sleep(Duration::ZERO).await;
+    Ok(work())
+}
@@ -0,0 +1,199 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use iced::{Size, Vector};
+use serde::{Deserialize, Serialize};
+
+/// Change of the orbit distance per line scrolled with the mouse wheel.
+const ZOOM_STEP: f32 = 1.2;
+
+/// Share of the distance to the target flown per line scrolled with the mouse wheel.
+const FLY_STEP: f32 = 0.1;
+
+const MIN_DISTANCE: f32 = 0.01;
+const MAX_DISTANCE: f32 = 1000.0;
+
+/// Keeps the camera from looking straight up or down, where its roll is undefined.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+const NEAR: f32 = 0.01;
+const FAR: f32 = 1000.0;
+
+/// The built-in camera passed to the shaders as `camera`, saved with the project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Camera {
+    pub mode: Mode,
+    /// The point the camera looks at.
+    pub target: [f32; 3],
+    /// Distance of the camera from the target.
+    pub distance: f32,
+    /// Rotation around the vertical axis in radians, at 0 the camera looks down -z.
+    pub yaw: f32,
+    /// Angle the camera looks down at in radians.
+    pub pitch: f32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            target: [0.0; 3],
+            distance: 4.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: 60.0,
+        }
+    }
+}
+
+impl Camera {
+    /// Whether dragging and scrolling in the viewer move the camera.
+    pub fn is_enabled(&self) -> bool {
+        self.mode != Mode::Off
+    }
+
+    /// Moves the camera back to where it started, keeping the mode.
+    pub fn reset(self) -> Self {
+        Self {
+            mode: self.mode,
+            ..Self::default()
+        }
+    }
+
+    /// Turns the camera by the logical pixels dragged, around the target when orbiting and in
+    /// place when flying.
+    pub fn rotate(self, delta: Vector, bounds: Size) -> Self {
+        // Dragging across the height of the viewer turns half around
+        let radians = PI / bounds.height;
+        let rotated = Self {
+            yaw: (self.yaw - delta.x * radians).rem_euclid(2.0 * PI),
+            pitch: (self.pitch + delta.y * radians).clamp(-MAX_PITCH, MAX_PITCH),
+            ..self
+        };
+
+        match self.mode {
+            Mode::Fly => Self {
+                target: add(self.position(), scale(rotated.forward(), self.distance)),
+                ..rotated
+            },
+            Mode::Off | Mode::Orbit => rotated,
+        }
+    }
+
+    /// Moves towards the target by the lines scrolled, away from it for negative ones.
+    pub fn zoom(self, lines: f32) -> Self {
+        match self.mode {
+            Mode::Fly => {
+                let step = scale(self.forward(), lines * FLY_STEP * self.distance);
+                Self {
+                    target: add(self.target, step),
+                    ..self
+                }
+            }
+            Mode::Off | Mode::Orbit => Self {
+                distance: (self.distance * ZOOM_STEP.powf(-lines))
+                    .clamp(MIN_DISTANCE, MAX_DISTANCE),
+                ..self
+            },
+        }
+    }
+
+    /// The position of the camera in world space.
+    pub fn position(&self) -> [f32; 3] {
+        add(self.target, scale(self.forward(), -self.distance))
+    }
+
+    /// The direction the camera looks in.
+    pub fn forward(&self) -> [f32; 3] {
+        [
+            -self.yaw.sin() * self.pitch.cos(),
+            -self.pitch.sin(),
+            -self.yaw.cos() * self.pitch.cos(),
+        ]
+    }
+
+    /// The world to view transform in columns, the camera looks down -z with y up.
+    pub fn view(&self) -> [[f32; 4]; 4] {
+        let forward = self.forward();
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let up = cross(right, forward);
+        let position = self.position();
+
+        [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [
+                -dot(right, position),
+                -dot(up, position),
+                dot(forward, position),
+                1.0,
+            ],
+        ]
+    }
+
+    /// The perspective projection from view to clip space in columns, with depth from 0 to 1.
+    pub fn projection(&self, aspect_ratio: f32) -> [[f32; 4]; 4] {
+        let focal_length = 1.0 / (self.fov.to_radians() / 2.0).tan();
+        let depth = FAR / (NEAR - FAR);
+
+        [
+            [focal_length / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, focal_length, 0.0, 0.0],
+            [0.0, 0.0, depth, -1.0],
+            [0.0, 0.0, NEAR * depth, 0.0],
+        ]
+    }
+}
+
+/// How dragging and scrolling in the viewer move the camera.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// The camera stays where it is and the viewer zooms the image instead.
+    #[default]
+    Off,
+    /// Dragging circles around the target and scrolling moves towards it.
+    Orbit,
+    /// Dragging looks around and scrolling flies forward.
+    Fly,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::Off, Mode::Orbit, Mode::Fly];
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Off => "Fixed camera",
+            Self::Orbit => "Orbit camera",
+            Self::Fly => "Fly camera",
+        })
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    a.map(|component| component * factor)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    scale(a, 1.0 / dot(a, a).sqrt())
+}
@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+use iced_wgpu::wgpu;
+
+use crate::audio;
+use crate::channel::{Channel, Filter, Input, Sampler, Wrap, CHANNEL_COUNT};
+
+/// The channel images uploaded to the GPU, bound as one bind group.
+pub struct Channels {
+    layout: wgpu::BindGroupLayout,
+    /// Bound in place of empty channels.
+    placeholder: wgpu::TextureView,
+    uploads: [Option<Upload>; CHANNEL_COUNT],
+    bind_group: wgpu::BindGroup,
+    version: usize,
+}
+
+impl Channels {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = (0..CHANNEL_COUNT as u32)
+            .flat_map(|index| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * index,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * index + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ]
+            })
+            .collect();
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bulin_canvas.pipeline.channels_bind_group_layout"),
+            entries: &entries,
+        });
+
+        let placeholder =
+            create_texture(device, 1, 1, 1).create_view(&wgpu::TextureViewDescriptor::default());
+
+        let empty: Vec<Option<wgpu::TextureView>> = (0..CHANNEL_COUNT).map(|_| None).collect();
+        let bind_group = create_bind_group(
+            device,
+            &layout,
+            &placeholder,
+            &empty,
+            &[Sampler::default(); CHANNEL_COUNT],
+        );
+
+        Self {
+            layout,
+            placeholder,
+            uploads: Default::default(),
+            bind_group,
+            version: 0,
+        }
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Uploads the channels that changed since the last version.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        version: usize,
+        channels: &[Option<Channel>; CHANNEL_COUNT],
+    ) {
+        if self.version == version {
+            return;
+        }
+        self.version = version;
+
+        for (upload, channel) in self.uploads.iter_mut().zip(channels) {
+            let is_current = match (upload.as_ref(), channel) {
+                (Some(upload), Some(channel)) => upload.is_of(channel),
+                (None, None) => true,
+                _ => false,
+            };
+            if is_current {
+                continue;
+            }
+
+            if let Some(upload) = upload.take() {
+                upload.texture.destroy();
+            }
+            *upload = channel
+                .as_ref()
+                .map(|channel| Upload::new(device, queue, channel));
+        }
+
+        let views: Vec<Option<wgpu::TextureView>> = self
+            .uploads
+            .iter()
+            .map(|upload| {
+                upload.as_ref().map(|upload| {
+                    upload
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default())
+                })
+            })
+            .collect();
+        let samplers: Vec<Sampler> = channels
+            .iter()
+            .map(|channel| {
+                channel
+                    .as_ref()
+                    .map(|channel| channel.sampler)
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        self.bind_group =
+            create_bind_group(device, &self.layout, &self.placeholder, &views, &samplers);
+    }
+
+    /// Writes the spectrum and waveform of the audio channels at the playback time, unless it
+    /// falls on the sample they were last written for.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, time: f32) {
+        for upload in self.uploads.iter_mut().flatten() {
+            let Input::Audio(audio) = &upload.input else {
+                continue;
+            };
+
+            let position = audio.position(time);
+            if upload.position != Some(position) {
+                write_texture(queue, &upload.texture, 0, &audio.analyze(position));
+                upload.position = Some(position);
+            }
+        }
+    }
+}
+
+/// The texture of a channel together with what it was uploaded from.
+struct Upload {
+    input: Input,
+    /// Whether the mip chain of an image was uploaded.
+    mipmaps: bool,
+    texture: wgpu::Texture,
+    /// The sample position an audio texture was last written for.
+    position: Option<usize>,
+}
+
+impl Upload {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, channel: &Channel) -> Self {
+        Self {
+            input: channel.input.clone(),
+            mipmaps: channel.sampler.mipmaps,
+            texture: upload(device, queue, channel),
+            position: None,
+        }
+    }
+
+    /// Whether the texture holds the input of the channel, the sampler is bound separately.
+    fn is_of(&self, channel: &Channel) -> bool {
+        match (&self.input, &channel.input) {
+            (Input::Image(uploaded), Input::Image(image)) => {
+                Arc::ptr_eq(uploaded, image) && self.mipmaps == channel.sampler.mipmaps
+            }
+            (Input::Audio(uploaded), Input::Audio(audio)) => Arc::ptr_eq(uploaded, audio),
+            _ => false,
+        }
+    }
+}
+
+fn upload(device: &wgpu::Device, queue: &wgpu::Queue, channel: &Channel) -> wgpu::Texture {
+    let image = match &channel.input {
+        Input::Image(image) => image,
+        // Written by `Channels::prepare` once the time of the frame is known
+        Input::Audio(_) => return create_texture(device, audio::WIDTH as u32, 2, 1),
+    };
+
+    let levels = image.levels();
+    let levels = if channel.sampler.mipmaps {
+        levels
+    } else {
+        &levels[..1]
+    };
+
+    let (width, height) = levels[0].dimensions();
+    let texture = create_texture(device, width, height, levels.len() as u32);
+
+    for (mip_level, level) in levels.iter().enumerate() {
+        write_texture(queue, &texture, mip_level as u32, level.as_raw());
+    }
+
+    texture
+}
+
+/// Replaces a mip level with tightly packed RGBA texels.
+fn write_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, mip_level: u32, texels: &[u8]) {
+    let size = texture
+        .size()
+        .mip_level_size(mip_level, texture.dimension());
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        texels,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.width),
+            rows_per_image: Some(size.height),
+        },
+        size,
+    );
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Channel Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    placeholder: &wgpu::TextureView,
+    views: &[Option<wgpu::TextureView>],
+    samplers: &[Sampler],
+) -> wgpu::BindGroup {
+    let samplers: Vec<wgpu::Sampler> = samplers
+        .iter()
+        .map(|sampler| create_sampler(device, sampler))
+        .collect();
+
+    let entries: Vec<wgpu::BindGroupEntry> = views
+        .iter()
+        .zip(&samplers)
+        .zip(0..)
+        .flat_map(|((view, sampler), index)| {
+            [
+                wgpu::BindGroupEntry {
+                    binding: 2 * index,
+                    resource: wgpu::BindingResource::TextureView(
+                        view.as_ref().unwrap_or(placeholder),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2 * index + 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ]
+        })
+        .collect();
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("bulin_canvas.pipeline.channels_bind_group"),
+        layout,
+        entries: &entries,
+    })
+}
+
+fn create_sampler(device: &wgpu::Device, sampler: &Sampler) -> wgpu::Sampler {
+    let address_mode = match sampler.wrap {
+        Wrap::Repeat => wgpu::AddressMode::Repeat,
+        Wrap::Clamp => wgpu::AddressMode::ClampToEdge,
+        Wrap::Mirror => wgpu::AddressMode::MirrorRepeat,
+    };
+    let filter = match sampler.filter {
+        Filter::Nearest => wgpu::FilterMode::Nearest,
+        Filter::Linear => wgpu::FilterMode::Linear,
+    };
+
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Channel Sampler"),
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: if sampler.mipmaps {
+            filter
+        } else {
+            wgpu::FilterMode::Nearest
+        },
+        ..wgpu::SamplerDescriptor::default()
+    })
+}
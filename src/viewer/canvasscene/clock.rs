@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+/// The playback time of a scene, advanced once per rendered frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clock {
+    pub time: Duration,
+    /// Time between the last two frames.
+    pub time_delta: Duration,
+    /// Frames since playback started.
+    pub frame: u32,
+}
+
+impl Clock {
+    /// A clock starting at the given time.
+    pub fn at(time: Duration) -> Self {
+        Self {
+            time,
+            ..Self::default()
+        }
+    }
+
+    /// Moves on to the next frame, `delta` after the current one.
+    pub fn advance(&mut self, delta: Duration) {
+        self.time += delta;
+        self.time_delta = delta;
+        self.frame = self.frame.wrapping_add(1);
+    }
+}
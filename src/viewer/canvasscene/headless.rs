@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use iced::{Rectangle, Size};
+use iced_wgpu::wgpu;
+
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::{Pass, ShaderUpdate};
+use crate::viewer::canvasscene::clock::Clock;
+use crate::viewer::canvasscene::mouse::Mouse;
+use crate::viewer::canvasscene::pipeline::Pipeline;
+use crate::viewer::canvasscene::uniforms::{CameraUniforms, DefaultUniforms};
+use crate::viewer::canvasscene::view::View;
+use crate::viewer::canvasscene::CanvasScene;
+
+/// The format of the saved images, the image pass is shown in it like on an sRGB surface.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Shader failed to compile:\n{}", format_diagnostics(.0))]
+    Shader(Vec<Diagnostic>),
+    #[error("No graphics adapter available")]
+    NoAdapter,
+    #[error("Could not create graphics device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("Could not read back the rendered image: {0}")]
+    Readback(#[from] wgpu::BufferAsyncError),
+}
+
+/// Renders frames of a scene without a window.
+///
+/// Buffer passes keep their contents between frames, so consecutive frames play like in the viewer.
+pub struct Renderer {
+    scene: CanvasScene,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: Pipeline,
+    /// The image pass after exposure, tone mapping and encoding, what the viewer displays.
+    target: wgpu::Texture,
+    size: Size<u32>,
+}
+
+impl Renderer {
+    /// Builds the scene described by the updates and allocates its targets at the given size.
+    pub async fn new(updates: Vec<ShaderUpdate>, size: Size<u32>) -> Result<Self, Error> {
+        let (mut scene, _) = CanvasScene::new(String::new());
+        apply(&mut scene, updates)?;
+
+        let (device, queue) = request_device().await?;
+
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let size = Size::new(
+            size.width.clamp(1, max_dimension),
+            size.height.clamp(1, max_dimension),
+        );
+        let mut pipeline = Pipeline::new(&device, FORMAT, size);
+        pipeline.set_precision(&device, scene.output().precision);
+        pipeline.set_view(&queue, &View::default());
+        pipeline.set_output(&queue, &scene.output());
+        let target = create_target(&device, size);
+
+        Ok(Self {
+            scene,
+            device,
+            queue,
+            pipeline,
+            target,
+            size,
+        })
+    }
+
+    /// Starts playback at the given time, the next frame is rendered at it.
+    pub fn seek(&mut self, time: Duration) {
+        self.scene.clock = Clock::at(time);
+    }
+
+    /// Moves playback on to the next frame.
+    pub fn advance(&mut self, delta: Duration) {
+        self.scene.clock.advance(delta);
+    }
+
+    /// The size of the rendered images, clamped to what the device can allocate.
+    pub fn size(&self) -> Size<u32> {
+        self.size
+    }
+
+    pub fn render(&mut self) -> Result<image::RgbaImage, Error> {
+        let scene = &self.scene;
+        self.pipeline.update_shaders(&self.device, &scene.active);
+        self.pipeline.update_channels(
+            &self.device,
+            &self.queue,
+            scene.channels_version,
+            &scene.channels,
+        );
+        self.pipeline.prepare_frame(
+            &self.queue,
+            &scene.active,
+            &DefaultUniforms::new(
+                self.size,
+                &Mouse::default(),
+                &scene.clock,
+                &View::default(),
+                1.0,
+            ),
+            &CameraUniforms::new(&scene.camera, self.size),
+            true,
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.pipeline.render(
+            &self
+                .target
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            &mut encoder,
+            &Rectangle {
+                x: 0,
+                y: 0,
+                width: self.size.width,
+                height: self.size.height,
+            },
+        );
+
+        read_texture(&self.device, &self.queue, encoder, &self.target)
+    }
+}
+
+/// Applies the updates to the scene, compiling every shader right away.
+///
+/// Only errors of the final shaders are reported, as updates can pass through intermediate
+/// states, e.g. a shader referencing custom uniforms that are added by a later update.
+pub(super) fn apply(scene: &mut CanvasScene, updates: Vec<ShaderUpdate>) -> Result<(), Error> {
+    let mut diagnostics: BTreeMap<Pass, Vec<Diagnostic>> = BTreeMap::new();
+
+    for update in updates {
+        for source in scene.update(update) {
+            let pass = source.pass();
+            match source.compile() {
+                Ok(compiled) => {
+                    scene.activate(compiled);
+                    diagnostics.remove(&pass);
+                }
+                Err(errors) => {
+                    diagnostics.insert(pass, errors);
+                }
+            }
+        }
+    }
+
+    let diagnostics: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .filter(|(pass, _)| scene.shaders.contains_key(pass))
+        .flat_map(|(_, diagnostics)| diagnostics)
+        .collect();
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Shader(diagnostics))
+    }
+}
+
+/// Creates a device of its own with the full limits of the adapter.
+pub(super) async fn request_device() -> Result<(wgpu::Device, wgpu::Queue), Error> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = request_adapter(&instance).await.ok_or(Error::NoAdapter)?;
+    let device = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("bulin_headless.device"),
+                required_limits: adapter.limits(),
+                ..wgpu::DeviceDescriptor::default()
+            },
+            None,
+        )
+        .await?;
+
+    Ok(device)
+}
+
+/// Prefers a hardware adapter and falls back to a software one.
+async fn request_adapter(instance: &wgpu::Instance) -> Option<wgpu::Adapter> {
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await;
+        if adapter.is_some() {
+            return adapter;
+        }
+    }
+    None
+}
+
+fn create_target(device: &wgpu::Device, size: Size<u32>) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("bulin_headless.target"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mut encoder: wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+) -> Result<image::RgbaImage, Error> {
+    let (width, height) = (texture.width(), texture.height());
+    let row_size = 4 * width;
+    let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("bulin_headless.readback"),
+        size: u64::from(padded_row_size) * u64::from(height),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("Buffer mapping callback was dropped")?;
+
+    let pixels = slice
+        .get_mapped_range()
+        .chunks(padded_row_size as usize)
+        .flat_map(|row| &row[..row_size as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+
+    Ok(image::RgbaImage::from_raw(width, height, pixels).expect("Buffer matches image size"))
+}
+
+fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
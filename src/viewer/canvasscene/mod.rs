@@ -1,65 +1,462 @@
+pub mod camera;
+mod channels;
+mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod mouse;
+pub mod output;
 mod pipeline;
+pub mod portability;
+mod readback;
+pub mod shadertoy;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sound;
+mod source;
+mod timer;
 mod uniforms;
+mod view;
 
+pub use camera::Camera;
+pub use clock::Clock;
+pub use output::Output;
+pub use readback::Pixel;
+pub use source::{CompiledShader, ShaderSource};
+pub use timer::Timings;
+pub use view::View;
+
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::std::Instant;
 
+use mouse::Mouse;
 use pipeline::Pipeline;
+use source::ActiveShader;
 
 use iced_wgpu::wgpu;
 
-use iced::mouse;
 use iced::widget::shader::{self, Viewport};
-use iced::Rectangle;
+use iced::{keyboard, mouse};
+use iced::{window, Event, Point, Rectangle, Size, Vector};
 
-use tracing::debug;
+use crate::channel::{Channel, CHANNEL_COUNT};
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::{Language, Pass, ShaderUpdate, UniformsUpdate};
+use crate::uniforms_editor::uniform::Uniform;
+use crate::viewer::canvasscene::uniforms::{CameraUniforms, CustomUniforms, DefaultUniforms};
+use crate::viewer::Message;
 
-use crate::shader_update::ShaderUpdate;
-use crate::viewer::canvasscene::uniforms::DefaultUniforms;
+/// The declarations prepended to the shaders of every pass, with the given custom uniforms.
+pub fn prelude(uniforms: &[Uniform], language: Language) -> String {
+    source::prelude(&CustomUniforms::new(uniforms), language)
+}
 
 #[derive(Clone)]
 pub struct CanvasScene {
     version: usize,
-    shader: Arc<String>,
+    shader_version: usize,
+    shaders: BTreeMap<Pass, String>,
+    uniforms: Vec<Uniform>,
+    language: Language,
+    /// The last shaders that passed validation, which are the ones being rendered.
+    active: BTreeMap<Pass, ActiveShader>,
+    /// The passes whose rendered shader lags behind the latest edits.
+    stale: BTreeSet<Pass>,
+    channels: [Option<Channel>; CHANNEL_COUNT],
+    channels_version: usize,
+    is_playing: bool,
+    clock: Clock,
+    view: View,
+    output: Output,
+    camera: Camera,
+    /// Written while rendering, shared by all primitives of the scene.
+    timings: Arc<Mutex<Timings>>,
+    is_inspecting: bool,
+    /// The pixel under the cursor while inspecting, written while rendering.
+    pixel: Arc<Mutex<Option<Pixel>>>,
+    /// The frames averaged into the shown image while accumulating, written while rendering.
+    samples: Arc<AtomicU32>,
 }
 
 impl CanvasScene {
-    pub fn new(shader: String) -> Self {
-        Self {
+    /// Creates a scene rendering the shader as its image pass, or an empty one along with the
+    /// diagnostics of the shader if it doesn't compile.
+    pub fn new(shader: String) -> (Self, Vec<Diagnostic>) {
+        let custom_uniforms = CustomUniforms::default();
+        let (compiled, diagnostics) =
+            match ShaderSource::new(Pass::Image, &shader, &custom_uniforms, Language::Wgsl)
+                .compile()
+            {
+                Ok(compiled) => (compiled, Vec::new()),
+                Err(diagnostics) => {
+                    let fallback = ShaderSource::new(
+                        Pass::Image,
+                        include_str!("shaders/empty_frag.wgsl"),
+                        &custom_uniforms,
+                        Language::Wgsl,
+                    )
+                    .compile()
+                    .expect("Compile fallback shader");
+                    (fallback, diagnostics)
+                }
+            };
+        let stale = if diagnostics.is_empty() {
+            BTreeSet::new()
+        } else {
+            BTreeSet::from([Pass::Image])
+        };
+
+        let scene = Self {
             version: 0,
-            shader: Arc::new(shader),
+            shader_version: 0,
+            shaders: BTreeMap::from([(Pass::Image, shader)]),
+            uniforms: Vec::new(),
+            language: Language::default(),
+            active: BTreeMap::from([(
+                Pass::Image,
+                ActiveShader {
+                    version: 0,
+                    compiled: Arc::new(compiled),
+                    custom_uniforms: Arc::new(custom_uniforms),
+                },
+            )]),
+            stale,
+            channels: Default::default(),
+            channels_version: 0,
+            is_playing: true,
+            clock: Clock::default(),
+            view: View::default(),
+            output: Output::default(),
+            camera: Camera::default(),
+            timings: Arc::default(),
+            is_inspecting: false,
+            pixel: Arc::default(),
+            samples: Arc::default(),
+        };
+        (scene, diagnostics)
+    }
+
+    /// Shows the scene at the clock, redrawing it every frame while playing.
+    pub fn set_clock(&mut self, clock: Clock, is_playing: bool) {
+        if clock != self.clock {
+            self.version += 1;
+        }
+        self.clock = clock;
+        self.is_playing = is_playing;
+    }
+
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    /// Pans and zooms the displayed image, rerendering it if the shader renders the view.
+    pub fn set_view(&mut self, view: View) {
+        if view.is_in_shader || self.view.is_in_shader {
+            self.version += 1;
+        }
+        self.view = view;
+    }
+
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// Changes how the image pass is stored and shown, rerendering it in a new format or when
+    /// accumulation is toggled.
+    pub fn set_output(&mut self, output: Output) {
+        if output.precision != self.output.precision
+            || output.is_accumulating != self.output.is_accumulating
+        {
+            self.version += 1;
+        }
+        self.output = output;
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Moves the camera the shaders see, rerendering the scene.
+    pub fn set_camera(&mut self, camera: Camera) {
+        if camera != self.camera {
+            self.version += 1;
         }
+        self.camera = camera;
     }
 
-    pub fn update(&mut self, message: ShaderUpdate) {
+    /// The latest measurements of the rendering.
+    pub fn timings(&self) -> Timings {
+        *self.timings.lock().unwrap()
+    }
+
+    /// Reads back the pixel under the cursor while set.
+    pub fn set_inspecting(&mut self, is_inspecting: bool) {
+        self.is_inspecting = is_inspecting;
+        *self.pixel.lock().unwrap() = None;
+    }
+
+    /// The pixel under the cursor, read back a few frames after it was rendered.
+    pub fn pixel(&self) -> Option<Pixel> {
+        *self.pixel.lock().unwrap()
+    }
+
+    /// The frames averaged into the shown image, 0 while not accumulating.
+    pub fn samples(&self) -> u32 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    /// Whether any rendered shader lags behind the latest edits.
+    pub fn is_stale(&self) -> bool {
+        !self.stale.is_empty()
+    }
+
+    /// The sources of the latest shaders of all passes.
+    pub fn sources(&self) -> Vec<ShaderSource> {
+        let custom_uniforms = CustomUniforms::new(&self.uniforms);
+        self.shaders
+            .iter()
+            .map(|(pass, shader)| ShaderSource::new(*pass, shader, &custom_uniforms, self.language))
+            .collect()
+    }
+
+    /// Applies the update and returns the new shader sources that have to be validated before
+    /// they can replace the rendered ones.
+    pub fn update(&mut self, message: ShaderUpdate) -> Vec<ShaderSource> {
         self.version += 1;
         match message {
-            ShaderUpdate::Shader(shader) => {
-                self.shader = Arc::new(shader);
+            ShaderUpdate::Shader(pass, shader) => {
+                let source = ShaderSource::new(
+                    pass,
+                    &shader,
+                    &CustomUniforms::new(&self.uniforms),
+                    self.language,
+                );
+                self.shaders.insert(pass, shader);
+                self.stale.insert(pass);
+
+                vec![source]
+            }
+            ShaderUpdate::RemovePass(pass) => {
+                // The image pass is what the viewer shows, so it can't be removed
+                if pass != Pass::Image {
+                    self.shaders.remove(&pass);
+                    self.active.remove(&pass);
+                    self.stale.remove(&pass);
+                }
+
+                Vec::new()
+            }
+            ShaderUpdate::Channel(index, channel) => {
+                self.channels[index] = channel;
+                self.channels_version += 1;
+
+                Vec::new()
             }
             ShaderUpdate::Uniforms(uniforms_update) => {
-                // TODO: Handle uniforms update if necessary
-                // This could involve updating the pipeline or uniforms state
-                // For now, we just log the update
-                debug!("Uniforms update received: {uniforms_update:?}");
+                match uniforms_update {
+                    UniformsUpdate::Add(uniform) => self.uniforms.push(uniform),
+                    UniformsUpdate::Update(name, uniform) => {
+                        if let Some(u) = self.uniforms.iter_mut().find(|u| u.name == name) {
+                            *u = uniform;
+                        }
+                    }
+                    UniformsUpdate::Remove(name) => self.uniforms.retain(|u| u.name != name),
+                    UniformsUpdate::Reset(uniforms) => self.uniforms = uniforms,
+                    UniformsUpdate::Clear => self.uniforms.clear(),
+                }
+
+                let custom_uniforms = Arc::new(CustomUniforms::new(&self.uniforms));
+                let mut sources = Vec::new();
+                for (pass, shader) in &self.shaders {
+                    match self.active.get_mut(pass) {
+                        Some(active)
+                            if active.compiled.declaration() == custom_uniforms.declaration() =>
+                        {
+                            active.custom_uniforms = custom_uniforms.clone();
+                        }
+                        _ => {
+                            self.stale.insert(*pass);
+                            sources.push(ShaderSource::new(
+                                *pass,
+                                shader,
+                                &custom_uniforms,
+                                self.language,
+                            ));
+                        }
+                    }
+                }
+
+                sources
+            }
+            // Playback is up to the viewer
+            ShaderUpdate::Transport(_) => Vec::new(),
+            ShaderUpdate::Output(output) => {
+                self.set_output(output);
+
+                Vec::new()
+            }
+            ShaderUpdate::Camera(camera) => {
+                self.set_camera(camera);
+
+                Vec::new()
+            }
+            ShaderUpdate::Language(language) => {
+                if language == self.language {
+                    return Vec::new();
+                }
+                self.language = language;
+
+                self.stale.extend(self.shaders.keys());
+                self.sources()
             }
         }
     }
+
+    /// Replaces the rendered shader of the pass it was compiled for.
+    ///
+    /// Shaders of removed passes or compiled for a different layout of the custom uniforms than
+    /// the current one are ignored.
+    pub fn activate(&mut self, compiled: CompiledShader) {
+        let pass = compiled.pass();
+        let custom_uniforms = CustomUniforms::new(&self.uniforms);
+        if !self.shaders.contains_key(&pass)
+            || custom_uniforms.declaration() != compiled.declaration()
+        {
+            return;
+        }
+
+        self.version += 1;
+        self.shader_version += 1;
+        self.active.insert(
+            pass,
+            ActiveShader {
+                version: self.shader_version,
+                compiled: Arc::new(compiled),
+                custom_uniforms: Arc::new(custom_uniforms),
+            },
+        );
+        self.stale.remove(&pass);
+    }
+}
+
+/// Input over the viewer.
+#[derive(Debug, Default)]
+pub struct Interaction {
+    mouse: Mouse,
+    /// Held modifiers, with shift a left drag pans the view.
+    modifiers: keyboard::Modifiers,
+    /// The last cursor position while panning.
+    pan: Option<Point>,
+    /// The last cursor position while turning the camera with the left button.
+    orbit: Option<Point>,
 }
 
-impl<Message> shader::Program<Message> for CanvasScene {
-    type State = ();
+impl shader::Program<Message> for CanvasScene {
+    type State = Interaction;
     type Primitive = Primitive;
 
+    /// The mouse wheel zooms and dragging with the left button while holding shift pans, other
+    /// mouse input goes to the shader. While the camera is enabled, the wheel and dragging with
+    /// the left button move it instead.
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: &Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<shader::Action<Message>> {
+        match event {
+            // Accumulating adds a sample every frame, even while paused
+            Event::Window(window::Event::RedrawRequested(_))
+                if self.is_playing || self.output.is_accumulating =>
+            {
+                Some(shader::Action::request_redraw())
+            }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let position = cursor.position_in(bounds)?;
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => *y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / view::PIXELS_PER_LINE,
+                };
+                let message = if self.camera.is_enabled() {
+                    Message::CameraChanged(self.camera.zoom(lines))
+                } else {
+                    Message::ViewChanged(self.view.zoom_at(position, bounds.size(), lines))
+                };
+                Some(shader::Action::publish(message).and_capture())
+            }
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = *modifiers;
+                None
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if state.modifiers.shift() =>
+            {
+                state.pan = Some(cursor.position_in(bounds)?);
+                Some(shader::Action::capture())
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.pan.is_some() =>
+            {
+                state.pan = None;
+                Some(shader::Action::capture())
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if self.camera.is_enabled() =>
+            {
+                state.orbit = Some(cursor.position_in(bounds)?);
+                Some(shader::Action::capture())
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.orbit.is_some() =>
+            {
+                state.orbit = None;
+                Some(shader::Action::capture())
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if state.orbit.is_some() => {
+                let position = cursor.position()? - Vector::new(bounds.x, bounds.y);
+                let last = state.orbit.replace(position)?;
+                let camera = self.camera.rotate(position - last, bounds.size());
+                Some(shader::Action::publish(Message::CameraChanged(camera)))
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if state.pan.is_some() => {
+                let position = cursor.position()? - Vector::new(bounds.x, bounds.y);
+                let last = state.pan.replace(position)?;
+                let view = self.view.pan(position - last, bounds.size());
+                Some(shader::Action::publish(Message::ViewChanged(view)))
+            }
+            Event::Mouse(_) if state.mouse.update(event, bounds, cursor) => {
+                Some(shader::Action::request_redraw())
+            }
+            _ => None,
+        }
+    }
+
     fn draw(
         &self,
-        _state: &Self::State,
+        state: &Self::State,
         _cursor: mouse::Cursor,
         _bounds: Rectangle,
     ) -> Self::Primitive {
         Primitive {
             version: self.version,
-            shader: self.shader.clone(),
+            shaders: self.active.clone(),
+            channels: self.channels.clone(),
+            channels_version: self.channels_version,
+            mouse: state.mouse,
+            is_playing: self.is_playing,
+            clock: self.clock,
+            view: self.view,
+            output: self.output,
+            camera: self.camera,
+            timings: self.timings.clone(),
+            pixel: self.is_inspecting.then(|| self.pixel.clone()),
+            samples: self.samples.clone(),
         }
     }
 }
@@ -67,7 +464,19 @@ impl<Message> shader::Program<Message> for CanvasScene {
 #[derive(Debug)]
 pub struct Primitive {
     version: usize,
-    shader: Arc<String>,
+    shaders: BTreeMap<Pass, ActiveShader>,
+    channels: [Option<Channel>; CHANNEL_COUNT],
+    channels_version: usize,
+    mouse: Mouse,
+    is_playing: bool,
+    clock: Clock,
+    view: View,
+    output: Output,
+    camera: Camera,
+    timings: Arc<Mutex<Timings>>,
+    /// Where the pixel under the cursor is written, if inspecting.
+    pixel: Option<Arc<Mutex<Option<Pixel>>>>,
+    samples: Arc<AtomicU32>,
 }
 
 struct PrimitiveVersion(usize);
@@ -93,32 +502,80 @@ impl shader::Primitive for Primitive {
         queue: &wgpu::Queue,
         format: wgpu::TextureFormat,
         storage: &mut shader::Storage,
-        _bounds: &Rectangle,
-        _viewport: &Viewport,
+        bounds: &Rectangle,
+        viewport: &Viewport,
     ) {
-        if !storage.has::<Pipeline>() {
+        let start = Instant::now();
+        let scale_factor = scale_factor(viewport);
+        let size = physical_size(device, bounds, scale_factor);
+        // The shader sees the cursor over the image it rendered, wherever that is displayed
+        let mouse = self.view.mouse(&self.mouse, bounds.size());
+        let mut default_uniforms =
+            DefaultUniforms::new(size, &mouse, &self.clock, &self.view, scale_factor);
+
+        let is_new = !storage.has::<Pipeline>();
+        if is_new {
             storage.store(PrimitiveVersion(self.version));
-            storage.store(Pipeline::new(
-                device,
-                queue,
-                format,
-                &self.shader,
-                &DefaultUniforms::default(),
-            ));
+            storage.store(self.mouse);
+            storage.store(Pipeline::new(device, format, size));
         }
 
-        // Ensure the pipeline is initialized
-        if &self.version > storage.get_mut::<PrimitiveVersion>().unwrap() {
-            let pipeline = storage.get_mut::<Pipeline>().unwrap();
-            pipeline.update_texture(
-                device,
-                queue,
-                format,
-                &self.shader,
-                &DefaultUniforms::default(),
-            );
-            storage.store(PrimitiveVersion(self.version));
+        let is_outdated = &self.version > storage.get::<PrimitiveVersion>().unwrap();
+        storage.store(PrimitiveVersion(self.version));
+
+        let last_mouse = *storage.get::<Mouse>().unwrap();
+        let is_mouse_moved = self.mouse != last_mouse;
+        storage.store(self.mouse);
+
+        let pipeline = storage.get_mut::<Pipeline>().unwrap();
+        let is_reformatted = pipeline.set_precision(device, self.output.precision);
+        let is_reallocated = pipeline.update_shaders(device, &self.shaders);
+        pipeline.update_channels(device, queue, self.channels_version, &self.channels);
+        let is_resized = pipeline.resize(device, size);
+        pipeline.set_view(queue, &self.view);
+        pipeline.set_output(queue, &self.output);
+
+        // Hovering doesn't start over, so the inspector can read the average
+        let is_dragged = is_mouse_moved && (self.mouse.buttons | last_mouse.buttons) != 0;
+        let sample_index = pipeline.accumulate(
+            device,
+            queue,
+            self.output.is_accumulating,
+            is_new || is_outdated || is_reformatted || is_reallocated || is_resized || is_dragged,
+        );
+        default_uniforms.sample_index = sample_index.unwrap_or(0);
+        self.samples.store(
+            sample_index.map_or(0, |index| index.saturating_add(1)),
+            Ordering::Relaxed,
+        );
+
+        let texel = self
+            .pixel
+            .as_ref()
+            .and_then(|_| texel_under(&mouse, bounds, size));
+        if let (Some(pixel), Some(read)) = (&self.pixel, pipeline.read_pixel(texel)) {
+            *pixel.lock().unwrap() = Some(read);
         }
+
+        let gpu_time = pipeline.prepare_frame(
+            queue,
+            &self.shaders,
+            &default_uniforms,
+            &CameraUniforms::new(&self.camera, size),
+            self.is_playing
+                || sample_index.is_some()
+                || is_new
+                || is_outdated
+                || is_reformatted
+                || is_reallocated
+                || is_resized
+                || is_mouse_moved,
+        );
+
+        let mut timings = self.timings.lock().unwrap();
+        timings.cpu = Some(start.elapsed());
+        timings.gpu = gpu_time.or(timings.gpu);
+        timings.has_timestamps = pipeline.has_timestamps();
     }
 
     fn render(
@@ -133,3 +590,36 @@ impl shader::Primitive for Primitive {
         pipeline.render(target, encoder, viewport);
     }
 }
+
+fn scale_factor(viewport: &Viewport) -> f32 {
+    viewport.physical_width() as f32 / viewport.logical_size().width
+}
+
+/// The size of the widget in physical pixels, clamped to what the device can allocate.
+fn physical_size(device: &wgpu::Device, bounds: &Rectangle, scale_factor: f32) -> Size<u32> {
+    let max_dimension = device.limits().max_texture_dimension_2d;
+
+    let dimension =
+        |logical: f32| ((logical * scale_factor).round() as u32).clamp(1, max_dimension);
+
+    Size::new(dimension(bounds.width), dimension(bounds.height))
+}
+
+/// The texel of the offscreen texture under the cursor, whose position is relative to the image
+/// at the size of the bounds, `None` if the cursor is beside the image.
+///
+/// The texture is the physical size of the bounds, clamped, so positions are scaled to it rather
+/// than multiplied by the scale factor.
+fn texel_under(mouse: &Mouse, bounds: &Rectangle, size: Size<u32>) -> Option<[u32; 2]> {
+    let texel = |position: f32, logical: f32, physical: u32| {
+        let texel = (position / logical * physical as f32).floor();
+        (0.0..physical as f32)
+            .contains(&texel)
+            .then_some(texel as u32)
+    };
+
+    Some([
+        texel(mouse.position.x, bounds.width, size.width)?,
+        texel(mouse.position.y, bounds.height, size.height)?,
+    ])
+}
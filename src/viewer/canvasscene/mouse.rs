@@ -0,0 +1,59 @@
+use iced::mouse::{self, Button};
+use iced::{Event, Point, Rectangle};
+
+/// Mouse input over the viewer in logical pixels relative to its top left corner.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Mouse {
+    /// The last known cursor position.
+    pub position: Point,
+    /// Where the last completed click was released.
+    pub click: Point,
+    /// Where the buttons currently held were first pressed.
+    pub drag_start: Point,
+    /// Bitmask of the held buttons: 1 left, 2 right, 4 middle.
+    pub buttons: u32,
+}
+
+impl Mouse {
+    /// Applies the event, returning whether the state changed.
+    pub fn update(&mut self, event: &Event, bounds: Rectangle, cursor: mouse::Cursor) -> bool {
+        let Event::Mouse(event) = event else {
+            return false;
+        };
+        let before = *self;
+
+        match event {
+            mouse::Event::CursorMoved { .. } => {
+                if let Some(position) = cursor.position_in(bounds) {
+                    self.position = position;
+                }
+            }
+            mouse::Event::ButtonPressed(button) => {
+                // Presses outside of the viewer neither start a drag nor count as click
+                if let Some(position) = cursor.position_in(bounds) {
+                    if self.buttons == 0 {
+                        self.drag_start = position;
+                    }
+                    self.position = position;
+                    self.buttons |= mask(*button);
+                }
+            }
+            mouse::Event::ButtonReleased(button) if self.buttons & mask(*button) != 0 => {
+                self.buttons &= !mask(*button);
+                self.click = self.position;
+            }
+            _ => {}
+        }
+
+        *self != before
+    }
+}
+
+fn mask(button: Button) -> u32 {
+    match button {
+        Button::Left => 1,
+        Button::Right => 2,
+        Button::Middle => 4,
+        _ => 0,
+    }
+}
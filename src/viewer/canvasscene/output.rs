@@ -0,0 +1,116 @@
+use iced_wgpu::wgpu;
+use serde::{Deserialize, Serialize};
+
+/// How the image pass is stored and how its colors are shown, saved with the project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Output {
+    pub precision: Precision,
+    pub tone_mapping: ToneMapping,
+    /// Stops of exposure applied before tone mapping, 0 leaves the colors as they are.
+    pub exposure: f32,
+    pub encoding: Encoding,
+    /// Shows the average of the frames rendered since the scene last changed, for Monte Carlo
+    /// renderers that add a sample every frame.
+    pub is_accumulating: bool,
+}
+
+impl Output {
+    /// Mirrors `struct Output` in `shaders/texture.wgsl`.
+    pub fn blit(&self, is_target_srgb: bool) -> [u32; 4] {
+        [
+            self.exposure.exp2().to_bits(),
+            self.tone_mapping as u32,
+            u32::from(self.encoding == Encoding::Srgb),
+            u32::from(is_target_srgb),
+        ]
+    }
+}
+
+/// The format the image pass renders into.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// The format of the surface, which clamps colors to 0 to 1 with 8 bits per channel.
+    #[default]
+    Surface,
+    Half,
+    Full,
+}
+
+impl Precision {
+    pub const ALL: [Precision; 3] = [Precision::Surface, Precision::Half, Precision::Full];
+
+    pub fn format(self, surface: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            Self::Surface => surface,
+            Self::Half => wgpu::TextureFormat::Rgba16Float,
+            Self::Full => wgpu::TextureFormat::Rgba32Float,
+        }
+    }
+}
+
+impl std::fmt::Display for Precision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Surface => "8 bit",
+            Self::Half => "16 bit float",
+            Self::Full => "32 bit float",
+        })
+    }
+}
+
+/// Maps the unbounded colors of HDR images to the displayable range.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapping {
+    /// Clamps colors above 1.
+    #[default]
+    None = 0,
+    Reinhard = 1,
+    /// Narkowicz' fit of the ACES filmic curve.
+    Aces = 2,
+    /// Hable's curve from Uncharted 2.
+    Filmic = 3,
+}
+
+impl ToneMapping {
+    pub const ALL: [ToneMapping; 4] = [
+        ToneMapping::None,
+        ToneMapping::Reinhard,
+        ToneMapping::Aces,
+        ToneMapping::Filmic,
+    ];
+}
+
+impl std::fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "No tone mapping",
+            Self::Reinhard => "Reinhard",
+            Self::Aces => "ACES",
+            Self::Filmic => "Filmic",
+        })
+    }
+}
+
+/// What the colors written by the image pass are.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Linear light, encoded for the display when shown.
+    #[default]
+    Linear,
+    /// Already encoded for the display, decoded before exposure and tone mapping.
+    Srgb,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 2] = [Encoding::Linear, Encoding::Srgb];
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Linear => "Linear output",
+            Self::Srgb => "sRGB output",
+        })
+    }
+}
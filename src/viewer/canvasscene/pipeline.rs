@@ -1,41 +1,413 @@
-use iced::Rectangle;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use iced::futures::FutureExt;
+use iced::{Rectangle, Size};
 use iced_wgpu::wgpu;
 use tracing::debug;
 
-use crate::viewer::canvasscene::uniforms::DefaultUniforms;
+use crate::channel::{Channel, CHANNEL_COUNT};
+use crate::shader_update::Pass;
+use crate::viewer::canvasscene::channels::Channels;
+use crate::viewer::canvasscene::output::{Output, Precision};
+use crate::viewer::canvasscene::readback::{Pixel, PixelReadback};
+use crate::viewer::canvasscene::source::{ActiveShader, CompiledShader};
+use crate::viewer::canvasscene::timer::GpuTimer;
+use crate::viewer::canvasscene::uniforms::{CameraUniforms, CustomUniforms, DefaultUniforms};
+use crate::viewer::canvasscene::view::View;
+
+/// Format of the buffer passes, a float format so feedback effects can accumulate values.
+const BUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Format of the running average of accumulated frames, precise enough for thousands of samples.
+const AVERAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
 
 pub struct Pipeline {
-    pipeline: wgpu::RenderPipeline,
-    texture_sampler: wgpu::Sampler,
+    /// The format of the surface the image is shown on.
+    format: wgpu::TextureFormat,
+    blit: Blit,
+    /// Pan and zoom of the displayed texture.
+    view: UniformBuffer,
+    /// Exposure, tone mapping and encoding of the displayed texture.
+    output: UniformBuffer,
     texture_bind_group: wgpu::BindGroup,
     texture: wgpu::Texture,
     texture_view: wgpu::TextureView,
+    /// Formats of the image pass the device failed to allocate.
+    unsupported_formats: Vec<wgpu::TextureFormat>,
+    buffers: Buffers,
+    channels: Channels,
+    /// The user shaders in the order they are executed.
+    passes: BTreeMap<Pass, Offscreen>,
+    render_offscreen: bool,
+    /// `None` if the device can't measure the offscreen passes.
+    timer: Option<GpuTimer>,
+    readback: PixelReadback,
+    /// Averages the frames of the image pass while set.
+    accumulation: Option<Accumulation>,
 }
 
 impl Pipeline {
-    pub fn new(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        format: wgpu::TextureFormat,
-        shader: &str,
-        default_uniforms: &DefaultUniforms,
-    ) -> Self {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: Size<u32>) -> Self {
         device.on_uncaptured_error(Box::new(|e| debug!("WGPU error: {e:#}")));
 
-        let texture = create_texture(device, format, default_uniforms);
+        let texture = create_texture(device, format, size);
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        let view = UniformBuffer::new(
+            device,
+            "bulin_canvas.pipeline.view",
+            std::mem::size_of::<[f32; 4]>() as u64,
+            wgpu::ShaderStages::FRAGMENT,
+        );
+        let output = UniformBuffer::new(
+            device,
+            "bulin_canvas.pipeline.output",
+            std::mem::size_of::<[u32; 4]>() as u64,
+            wgpu::ShaderStages::FRAGMENT,
+        );
 
-        update_texture(
+        let blit = Blit::new(
             device,
-            queue,
             format,
-            &texture_view,
-            shader,
-            default_uniforms,
+            is_filterable(device, texture.format()),
+            [&view.layout, &output.layout],
+        );
+        let texture_bind_group = blit.bind_group(device, &texture_view);
+
+        Self {
+            format,
+            blit,
+            view,
+            output,
+            texture_bind_group,
+            texture,
+            texture_view,
+            unsupported_formats: Vec::new(),
+            buffers: Buffers::new(device),
+            channels: Channels::new(device),
+            passes: BTreeMap::new(),
+            render_offscreen: true,
+            timer: GpuTimer::new(device),
+            readback: PixelReadback::new(device),
+            accumulation: None,
+        }
+    }
+
+    /// Recompiles the passes whose shader version changed and drops the removed ones.
+    ///
+    /// Returns `true` if the set of buffer passes changed, which reallocates their textures.
+    pub fn update_shaders(
+        &mut self,
+        device: &wgpu::Device,
+        shaders: &BTreeMap<Pass, ActiveShader>,
+    ) -> bool {
+        let mut passes = BTreeMap::new();
+        for (pass, shader) in shaders {
+            let offscreen = match self.passes.remove(pass) {
+                Some(offscreen) if offscreen.shader_version == shader.version => offscreen,
+                _ => Offscreen::new(
+                    device,
+                    target_format(*pass, self.texture.format()),
+                    shader.version,
+                    &shader.compiled,
+                    &shader.custom_uniforms,
+                    [&self.buffers.layout, self.channels.layout()],
+                ),
+            };
+            passes.insert(*pass, offscreen);
+        }
+        self.passes = passes;
+
+        let buffers: Vec<Pass> = self
+            .passes
+            .keys()
+            .copied()
+            .filter(|pass| *pass != Pass::Image)
+            .collect();
+        if self.buffers.passes().eq(buffers.iter().copied()) {
+            return false;
+        }
+
+        self.buffers
+            .allocate(device, &buffers, texture_size_of(&self.texture));
+        true
+    }
+
+    /// Uploads the channel images if they changed since the last frame.
+    pub fn update_channels(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        version: usize,
+        channels: &[Option<Channel>; CHANNEL_COUNT],
+    ) {
+        self.channels.update(device, queue, version, channels);
+    }
+
+    /// Reallocates the offscreen render target if the requested size differs from the current one.
+    ///
+    /// Returns `true` if the texture was recreated and therefore needs to be rerendered.
+    pub fn resize(&mut self, device: &wgpu::Device, size: Size<u32>) -> bool {
+        if texture_size_of(&self.texture) == size {
+            return false;
+        }
+
+        self.replace_texture(device, create_texture(device, self.texture.format(), size));
+
+        let buffers: Vec<Pass> = self.buffers.passes().collect();
+        self.buffers.allocate(device, &buffers, size);
+
+        true
+    }
+
+    /// Renders the image pass into the format of the precision from the next frame on, unless the
+    /// device can't render to it.
+    ///
+    /// Returns `true` if the texture was recreated and therefore needs to be rerendered.
+    pub fn set_precision(&mut self, device: &wgpu::Device, precision: Precision) -> bool {
+        let format = precision.format(self.format);
+        if self.texture.format() == format || self.unsupported_formats.contains(&format) {
+            return false;
+        }
+
+        // Downlevel backends like WebGL 2 can't render to 32 bit floats
+        let texture = match try_create_texture(device, format, texture_size_of(&self.texture)) {
+            Ok(texture) => texture,
+            Err(error) => {
+                debug!("Keeping {:?} target: {error}", self.texture.format());
+                self.unsupported_formats.push(format);
+                return false;
+            }
+        };
+
+        self.replace_texture(device, texture);
+        // Compiled for the new format by the next update of the shaders
+        self.passes.remove(&Pass::Image);
+
+        true
+    }
+
+    /// Averages the frames of the image pass while accumulating, starting over if reset.
+    ///
+    /// Returns the index of the sample rendered in the coming frame, `None` if not accumulating.
+    pub fn accumulate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        is_accumulating: bool,
+        is_reset: bool,
+    ) -> Option<u32> {
+        if !is_accumulating {
+            self.accumulation = None;
+            return None;
+        }
+
+        let accumulation = self.accumulation.get_or_insert_with(|| {
+            Accumulation::new(
+                device,
+                self.texture.format(),
+                texture_size_of(&self.texture),
+            )
+        });
+        Some(accumulation.prepare(queue, is_reset))
+    }
+
+    /// Sets the exposure, tone mapping and encoding of the displayed texture.
+    pub fn set_output(&self, queue: &wgpu::Queue, output: &Output) {
+        queue.write_buffer(
+            &self.output.buffer,
+            0,
+            bytemuck::bytes_of(&output.blit(self.format.is_srgb())),
+        );
+    }
+
+    /// Writes the uniforms for the next frame.
+    ///
+    /// The offscreen passes are only executed in the following [`Pipeline::render`] if `rerender`
+    /// is set, otherwise the last rendered texture is displayed again.
+    ///
+    /// Returns the GPU time of the offscreen passes of an earlier frame once it was read back.
+    pub fn prepare_frame(
+        &mut self,
+        queue: &wgpu::Queue,
+        shaders: &BTreeMap<Pass, ActiveShader>,
+        default_uniforms: &DefaultUniforms,
+        camera: &CameraUniforms,
+        rerender: bool,
+    ) -> Option<Duration> {
+        self.render_offscreen = rerender;
+        let gpu_time = self
+            .timer
+            .as_mut()
+            .and_then(|timer| timer.prepare(queue, rerender));
+
+        if rerender {
+            self.buffers.advance();
+            self.channels.prepare(queue, default_uniforms.time);
+
+            for (pass, offscreen) in &self.passes {
+                if let Some(shader) = shaders.get(pass) {
+                    offscreen.write_uniforms(
+                        queue,
+                        &shader.custom_uniforms,
+                        default_uniforms,
+                        camera,
+                    );
+                }
+            }
+        }
+
+        gpu_time
+    }
+
+    /// Whether the offscreen passes are measured with timestamp queries.
+    pub fn has_timestamps(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Executes the user shaders, rendering the image pass into the offscreen texture.
+    pub fn render_passes(&self, encoder: &mut wgpu::CommandEncoder) {
+        let last = self.passes.len().saturating_sub(1);
+        for (index, (pass, offscreen)) in self.passes.iter().enumerate() {
+            let target = match pass {
+                Pass::Image => self
+                    .accumulation
+                    .as_ref()
+                    .map_or(&self.texture_view, Accumulation::target),
+                _ => self.buffers.target(*pass),
+            };
+            offscreen.render(
+                encoder,
+                target,
+                [self.buffers.bind_group(*pass), self.channels.bind_group()],
+                self.timer
+                    .as_ref()
+                    .and_then(|timer| timer.timestamp_writes(index == 0, index == last)),
+            );
+        }
+
+        if let Some(accumulation) = &self.accumulation {
+            accumulation.render(encoder, &self.texture_view);
+        }
+
+        if let Some(timer) = &self.timer {
+            timer.resolve(encoder);
+        }
+    }
+
+    /// Pans and zooms the displayed texture.
+    pub fn set_view(&self, queue: &wgpu::Queue, view: &View) {
+        queue.write_buffer(&self.view.buffer, 0, bytemuck::bytes_of(&view.blit()));
+    }
+
+    /// Copies the texel of the image pass in the coming frame and returns the pixel of an earlier
+    /// one once it was read back.
+    pub fn read_pixel(&mut self, texel: Option<[u32; 2]>) -> Option<Pixel> {
+        self.readback.prepare(&self.texture, texel)
+    }
+
+    /// Replaces the offscreen texture, recreating the blit if it can't sample the new format.
+    fn replace_texture(&mut self, device: &wgpu::Device, texture: wgpu::Texture) {
+        self.texture.destroy();
+        self.texture = texture;
+        self.texture_view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let is_filterable = is_filterable(device, self.texture.format());
+        if is_filterable != self.blit.is_filterable {
+            self.blit = Blit::new(
+                device,
+                self.format,
+                is_filterable,
+                [&self.view.layout, &self.output.layout],
+            );
+        }
+        self.texture_bind_group = self.blit.bind_group(device, &self.texture_view);
+
+        // Starts over at the new size or format
+        if self.accumulation.is_some() {
+            self.accumulation = Some(Accumulation::new(
+                device,
+                self.texture.format(),
+                texture_size_of(&self.texture),
+            ));
+        }
+    }
+
+    pub fn render(
+        &self,
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        viewport: &Rectangle<u32>,
+    ) {
+        if self.render_offscreen {
+            self.render_passes(encoder);
+        }
+        self.readback.copy(encoder, &self.texture);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("fill color test"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&self.blit.pipeline);
+
+        pass.set_viewport(
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            0.0,
+            1.0,
         );
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        pass.set_bind_group(0, Some(&self.texture_bind_group), &[]);
+        pass.set_bind_group(1, Some(&self.view.bind_group), &[]);
+        pass.set_bind_group(2, Some(&self.output.bind_group), &[]);
+
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Shows the offscreen texture on the surface.
+struct Blit {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Whether the texture is filtered when zoomed out, which not all float formats support.
+    is_filterable: bool,
+}
+
+impl Blit {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        is_filterable: bool,
+        [view_layout, output_layout]: [&wgpu::BindGroupLayout; 2],
+    ) -> Self {
+        // Shows single texels when zoomed in and filters when zoomed out
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            min_filter: if is_filterable {
+                wgpu::FilterMode::Linear
+            } else {
+                wgpu::FilterMode::Nearest
+            },
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("BindGroupLayout"),
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -44,36 +416,28 @@ impl Pipeline {
                     ty: wgpu::BindingType::Texture {
                         multisampled: false,
                         view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        sample_type: wgpu::TextureSampleType::Float {
+                            filterable: is_filterable,
+                        },
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
                     visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(if is_filterable {
+                        wgpu::SamplerBindingType::Filtering
+                    } else {
+                        wgpu::SamplerBindingType::NonFiltering
+                    }),
                     count: None,
                 },
             ],
         });
 
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Postprocess Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-            ],
-        });
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Quad Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&layout, view_layout, output_layout],
             push_constant_ranges: &[],
         });
 
@@ -110,192 +474,715 @@ impl Pipeline {
 
         Self {
             pipeline,
-            texture_sampler,
-            texture_bind_group,
-            texture,
-            texture_view,
+            layout,
+            sampler,
+            is_filterable,
         }
     }
 
-    pub fn update_texture(
+    fn bind_group(
         &self,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        texture_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Postprocess Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// The compiled user shader together with the uniform buffers it reads from.
+struct Offscreen {
+    pipeline: wgpu::RenderPipeline,
+    /// Group 0 with the built-in uniforms and the camera.
+    uniforms: BuiltIns,
+    customs: Option<UniformBuffer>,
+    shader_version: usize,
+}
+
+impl Offscreen {
+    fn new(
+        device: &wgpu::Device,
         format: wgpu::TextureFormat,
-        shader: &str,
+        shader_version: usize,
+        shader: &CompiledShader,
+        custom_uniforms: &CustomUniforms,
+        layouts: [&wgpu::BindGroupLayout; 2],
+    ) -> Self {
+        let uniforms = BuiltIns::new(device);
+
+        let customs = (!custom_uniforms.is_empty()).then(|| {
+            UniformBuffer::new(
+                device,
+                "bulin_canvas.pipeline.customs",
+                custom_uniforms.bytes().len() as u64,
+                wgpu::ShaderStages::FRAGMENT,
+            )
+        });
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Offscreen Shader"),
+            source: wgpu::ShaderSource::Naga(Cow::Owned(shader.module().clone())),
+        });
+        let vertex_module = shader.vertex_module().map(|module| {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Offscreen Vertex Shader"),
+                source: wgpu::ShaderSource::Naga(Cow::Owned(module.clone())),
+            })
+        });
+
+        let bind_group_layouts: Vec<&wgpu::BindGroupLayout> = std::iter::once(&uniforms.layout)
+            .chain(layouts)
+            .chain(customs.as_ref().map(|buffer| &buffer.layout))
+            .collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Offscreen Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Offscreen Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vertex_module.as_ref().unwrap_or(&shader_module),
+                entry_point: None,
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // Replaces without blending, which 32 bit float targets don't support
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            uniforms,
+            customs,
+            shader_version,
+        }
+    }
+
+    fn write_uniforms(
+        &self,
+        queue: &wgpu::Queue,
+        custom_uniforms: &CustomUniforms,
         default_uniforms: &DefaultUniforms,
+        camera: &CameraUniforms,
     ) {
-        update_texture(
-            device,
-            queue,
-            format,
-            &self.texture_view,
-            shader,
-            default_uniforms,
+        queue.write_buffer(
+            &self.uniforms.buffer,
+            0,
+            bytemuck::bytes_of(default_uniforms),
         );
+        queue.write_buffer(&self.uniforms.camera, 0, bytemuck::bytes_of(camera));
+        if let Some(customs) = &self.customs {
+            queue.write_buffer(&customs.buffer, 0, custom_uniforms.bytes());
+        }
     }
 
-    pub fn render(
+    fn render(
         &self,
-        target: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
-        viewport: &Rectangle<u32>,
+        texture_view: &wgpu::TextureView,
+        bind_groups: [&wgpu::BindGroup; 2],
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
     ) {
-        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("fill color test"),
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Offscreen Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: target,
+                view: texture_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                     store: wgpu::StoreOp::Store,
                 },
             })],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
-        pass.set_pipeline(&self.pipeline);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.uniforms.bind_group, &[]);
+        render_pass.set_bind_group(1, bind_groups[0], &[]);
+        render_pass.set_bind_group(2, bind_groups[1], &[]);
+        if let Some(customs) = &self.customs {
+            render_pass.set_bind_group(3, &customs.bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1); // full screen triangle
+    }
+}
 
-        pass.set_viewport(
-            viewport.x as f32,
-            viewport.y as f32,
-            viewport.width as f32,
-            viewport.height as f32,
-            0.0,
-            1.0,
+/// The ping-pong render targets of the buffer passes.
+///
+/// Every frame the buffers render into one of their two textures while the other one holds the
+/// previous frame. Passes see the current frame of the buffers executed before them and the
+/// previous frame of all others, including themselves.
+struct Buffers {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Bound in place of the buffers a project doesn't use.
+    placeholder: wgpu::TextureView,
+    targets: BTreeMap<Pass, [Target; 2]>,
+    bind_groups: BTreeMap<Pass, [wgpu::BindGroup; 2]>,
+    /// Index of the targets rendered in the current frame.
+    frame: usize,
+}
+
+struct Target {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl Buffers {
+    fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bulin_canvas.pipeline.buffers_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        let placeholder = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Buffer Placeholder"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: BUFFER_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut buffers = Self {
+            layout,
+            sampler,
+            placeholder,
+            targets: BTreeMap::new(),
+            bind_groups: BTreeMap::new(),
+            frame: 0,
+        };
+        buffers.allocate(device, &[], Size::new(1, 1));
+        buffers
+    }
+
+    fn passes(&self) -> impl Iterator<Item = Pass> + '_ {
+        self.targets.keys().copied()
+    }
+
+    /// Recreates the targets of the given buffer passes, which start out cleared.
+    fn allocate(&mut self, device: &wgpu::Device, passes: &[Pass], size: Size<u32>) {
+        for target in self.targets.values().flatten() {
+            target.texture.destroy();
+        }
+
+        self.targets = passes
+            .iter()
+            .map(|pass| {
+                let target = || {
+                    let texture = create_texture(device, BUFFER_FORMAT, size);
+                    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                    Target { texture, view }
+                };
+                (*pass, [target(), target()])
+            })
+            .collect();
+
+        self.bind_groups = Pass::ALL
+            .into_iter()
+            .map(|pass| {
+                (
+                    pass,
+                    [
+                        self.create_bind_group(device, pass, 0),
+                        self.create_bind_group(device, pass, 1),
+                    ],
+                )
+            })
+            .collect();
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        pass: Pass,
+        frame: usize,
+    ) -> wgpu::BindGroup {
+        let views: Vec<&wgpu::TextureView> = Pass::BUFFERS
+            .into_iter()
+            .map(|buffer| match self.targets.get(&buffer) {
+                Some(targets) if buffer < pass => &targets[frame].view,
+                Some(targets) => &targets[1 - frame].view,
+                None => &self.placeholder,
+            })
+            .collect();
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Sampler(&self.sampler),
+        }];
+        entries.extend(
+            views
+                .into_iter()
+                .zip(1..)
+                .map(|(view, binding)| wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }),
         );
 
-        pass.set_bind_group(0, Some(&self.texture_bind_group), &[]);
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bulin_canvas.pipeline.buffers_bind_group"),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
 
-        pass.draw(0..3, 0..1);
+    /// Swaps the targets rendered to with the ones holding the previous frame.
+    fn advance(&mut self) {
+        self.frame = 1 - self.frame;
+    }
+
+    fn target(&self, pass: Pass) -> &wgpu::TextureView {
+        &self.targets[&pass][self.frame].view
+    }
+
+    fn bind_group(&self, pass: Pass) -> &wgpu::BindGroup {
+        &self.bind_groups[&pass][self.frame]
     }
 }
 
-pub fn update_texture(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
-    format: wgpu::TextureFormat,
-    texture_view: &wgpu::TextureView,
-    shader: &str,
-    default_uniforms: &DefaultUniforms,
-) {
-    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: Some("bulin_canvas.pipeline.uniforms"),
-        size: std::mem::size_of::<DefaultUniforms>() as u64,
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false,
-    });
-
-    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("bulin_canvas.pipeline.uniform_bind_group_layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
+/// Averages the frames of the image pass, for Monte Carlo renderers that add a sample per frame.
+///
+/// The image pass renders into `current` instead of the displayed texture, which receives the
+/// average of the frames since the last reset.
+struct Accumulation {
+    pipeline: wgpu::RenderPipeline,
+    /// The share of the current frame in the average.
+    weight: UniformBuffer,
+    current: Target,
+    /// Every frame one of them is rendered while the other holds the previous average.
+    averages: [Target; 2],
+    bind_groups: [wgpu::BindGroup; 2],
+    /// Index of the average rendered in the current frame.
+    frame: usize,
+    /// Frames averaged including the current one.
+    samples: u32,
+}
+
+impl Accumulation {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: Size<u32>) -> Self {
+        let target = |texture: wgpu::Texture| Target {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+        };
+
+        let current = target(create_texture(device, format, size));
+        // Downlevel backends like WebGL 2 can't render to 32 bit floats
+        let average = try_create_texture(device, AVERAGE_FORMAT, size).unwrap_or_else(|error| {
+            debug!("Averaging in {BUFFER_FORMAT:?}: {error}");
+            create_texture(device, BUFFER_FORMAT, size)
+        });
+        let average_format = average.format();
+        let averages = [
+            target(average),
+            target(create_texture(device, average_format, size)),
+        ];
+
+        let weight = UniformBuffer::new(
+            device,
+            "bulin_canvas.pipeline.accumulation",
+            std::mem::size_of::<[f32; 4]>() as u64,
+            wgpu::ShaderStages::FRAGMENT,
+        );
+
+        // Loaded rather than sampled, so any float format can be read
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
             },
             count: None,
-        }],
-    });
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bulin_canvas.pipeline.accumulation_textures_bind_group_layout"),
+            entries: &[texture_entry(0), texture_entry(1)],
+        });
+        let bind_group = |previous: &Target| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bulin_canvas.pipeline.accumulation_textures_bind_group"),
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&current.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&previous.view),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [bind_group(&averages[1]), bind_group(&averages[0])];
 
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("bulin_canvas.pipeline.uniform_bind_group"),
-        layout: &layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
-        }],
-    });
-
-    let shader = format!(
-        "{}\n{}\n{}",
-        include_str!("shaders/uniforms.wgsl"),
-        include_str!("shaders/vertex_shader.wgsl"),
-        shader
-    );
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Accumulation Pipeline Layout"),
+            bind_group_layouts: &[&layout, &weight.layout],
+            push_constant_ranges: &[],
+        });
 
-    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Offscreen Shader"),
-        source: wgpu::ShaderSource::Wgsl(shader.into()),
-    });
-
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("Offscreen Pipeline Layout"),
-        bind_group_layouts: &[&layout],
-        push_constant_ranges: &[],
-    });
-
-    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Offscreen Pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader_module,
-            entry_point: None,
-            buffers: &[],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader_module,
-            entry_point: None,
-            targets: &[Some(wgpu::ColorTargetState {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Accumulation Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/accumulate.wgsl").into()),
+        });
+
+        let color_target = |format| {
+            Some(wgpu::ColorTargetState {
                 format,
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: None,
                 write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        }),
-        primitive: wgpu::PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-        cache: None,
-    });
-
-    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("Offscreen Encoder"),
-    });
-
-    queue.write_buffer(&buffer, 0, bytemuck::bytes_of(default_uniforms));
-
-    {
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Offscreen Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: texture_view,
+            })
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Accumulation Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: None,
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: None,
+                targets: &[color_target(average_format), color_target(format)],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            weight,
+            current,
+            averages,
+            bind_groups,
+            frame: 0,
+            samples: 0,
+        }
+    }
+
+    /// Advances to the next sample, starting over if reset, and returns its index.
+    fn prepare(&mut self, queue: &wgpu::Queue, is_reset: bool) -> u32 {
+        if is_reset {
+            self.samples = 0;
+        }
+        let index = self.samples;
+        self.samples = self.samples.saturating_add(1);
+        self.frame = 1 - self.frame;
+
+        let weight = 1.0 / self.samples as f32;
+        queue.write_buffer(
+            &self.weight.buffer,
+            0,
+            bytemuck::bytes_of(&[weight, 0.0, 0.0, 0.0]),
+        );
+
+        index
+    }
+
+    /// The texture the image pass renders the current frame into.
+    fn target(&self) -> &wgpu::TextureView {
+        &self.current.view
+    }
+
+    /// Averages the current frame into the next average and the displayed image.
+    fn render(&self, encoder: &mut wgpu::CommandEncoder, image: &wgpu::TextureView) {
+        let attachment = |view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                     store: wgpu::StoreOp::Store,
                 },
-            })],
+            })
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Accumulation Pass"),
+            color_attachments: &[
+                attachment(&self.averages[self.frame].view),
+                attachment(image),
+            ],
             depth_stencil_attachment: None,
             timestamp_writes: None,
             occlusion_query_set: None,
         });
 
-        render_pass.set_pipeline(&pipeline);
-        render_pass.set_bind_group(0, &bind_group, &[]);
-        render_pass.draw(0..3, 0..1); // full screen triangle
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.frame], &[]);
+        pass.set_bind_group(1, &self.weight.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// The uniforms every pass sees in group 0, `uniforms` and `camera`.
+struct BuiltIns {
+    buffer: wgpu::Buffer,
+    camera: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl BuiltIns {
+    fn new(device: &wgpu::Device) -> Self {
+        let buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let buffer_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let uniforms = buffer(
+            "bulin_canvas.pipeline.uniforms",
+            std::mem::size_of::<DefaultUniforms>(),
+        );
+        let camera = buffer(
+            "bulin_canvas.pipeline.camera",
+            std::mem::size_of::<CameraUniforms>(),
+        );
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bulin_canvas.pipeline.uniforms_bind_group_layout"),
+            entries: &[buffer_entry(0), buffer_entry(1)],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bulin_canvas.pipeline.uniforms_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniforms.as_entire_buffer_binding()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(camera.as_entire_buffer_binding()),
+                },
+            ],
+        });
+
+        Self {
+            buffer: uniforms,
+            camera,
+            layout,
+            bind_group,
+        }
     }
+}
+
+struct UniformBuffer {
+    buffer: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl UniformBuffer {
+    fn new(device: &wgpu::Device, label: &str, size: u64, visibility: wgpu::ShaderStages) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label}_bind_group_layout")),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{label}_bind_group")),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+            }],
+        });
 
-    // Wait for the texture to be ready
-    queue.submit(Some(encoder.finish()));
+        Self {
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+}
+
+fn target_format(pass: Pass, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+    match pass {
+        Pass::Image => format,
+        _ => BUFFER_FORMAT,
+    }
+}
+
+fn texture_size_of(texture: &wgpu::Texture) -> Size<u32> {
+    Size::new(texture.width(), texture.height())
+}
+
+fn is_filterable(device: &wgpu::Device, format: wgpu::TextureFormat) -> bool {
+    format
+        .guaranteed_format_features(device.features())
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE)
+}
+
+/// Whether the device can render to the format.
+///
+/// WebGL 2 renders to 32 bit floats only with an extension the device doesn't report, so they
+/// count as unsupported on devices without compute shaders like it.
+fn is_renderable(device: &wgpu::Device, format: wgpu::TextureFormat) -> bool {
+    let is_float32 = matches!(
+        format,
+        wgpu::TextureFormat::R32Float
+            | wgpu::TextureFormat::Rg32Float
+            | wgpu::TextureFormat::Rgba32Float
+    );
+
+    format
+        .guaranteed_format_features(device.features())
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        && !(is_float32 && device.limits().max_compute_invocations_per_workgroup == 0)
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TargetError {
+    #[error("the device can't render to {0:?}")]
+    Unsupported(wgpu::TextureFormat),
+    #[error(transparent)]
+    Validation(wgpu::Error),
+}
+
+/// Creates the texture unless the device can't render to its format.
+fn try_create_texture(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    size: Size<u32>,
+) -> Result<wgpu::Texture, TargetError> {
+    if !is_renderable(device, format) {
+        return Err(TargetError::Unsupported(format));
+    }
+
+    // Catches what the format features don't tell on native, the error scope resolves later on
+    // the web
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let texture = create_texture(device, format, size);
+    match device.pop_error_scope().now_or_never().flatten() {
+        Some(error) => {
+            texture.destroy();
+            Err(TargetError::Validation(error))
+        }
+        None => Ok(texture),
+    }
 }
 
 fn create_texture(
     device: &wgpu::Device,
     format: wgpu::TextureFormat,
-    default_uniforms: &DefaultUniforms,
+    size: Size<u32>,
 ) -> wgpu::Texture {
     device.create_texture(&wgpu::TextureDescriptor {
         label: Some("Offscreen Render Target"),
         size: wgpu::Extent3d {
-            width: default_uniforms.resolution[0] as u32,
-            height: default_uniforms.resolution[1] as u32,
+            width: size.width,
+            height: size.height,
             depth_or_array_layers: 1,
         },
         mip_level_count: 1,
@@ -0,0 +1,227 @@
+use std::error::Error;
+use std::fmt;
+
+use iced_wgpu::wgpu;
+use naga::back::{glsl, hlsl, msl, spv};
+use naga::valid::{Capabilities, ModuleInfo};
+use naga::{AddressSpace, DerivativeControl, Expression};
+
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::Pass;
+use crate::viewer::canvasscene::source::ShaderSource;
+
+/// A target shaders may end up on besides the device they are developed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Validation with the capabilities and limits of the `webgl` build.
+    WebGl2,
+    SpirV,
+    Msl,
+    Hlsl,
+    GlslEs,
+}
+
+impl Target {
+    pub const ALL: [Target; 5] = [
+        Target::WebGl2,
+        Target::SpirV,
+        Target::Msl,
+        Target::Hlsl,
+        Target::GlslEs,
+    ];
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WebGl2 => "WebGL2",
+            Self::SpirV => "SPIR-V",
+            Self::Msl => "MSL",
+            Self::Hlsl => "HLSL",
+            Self::GlslEs => "GLSL ES 3.00",
+        })
+    }
+}
+
+/// The findings of all passes for a target.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub target: Target,
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Checks the shaders against every target.
+///
+/// Fails with the compile errors if a shader doesn't even compile for the current device, as
+/// there would be nothing to translate.
+pub fn check(sources: Vec<ShaderSource>) -> Result<Vec<Report>, Vec<Diagnostic>> {
+    let mut modules = Vec::new();
+    let mut errors = Vec::new();
+    for source in sources {
+        let compiled = source.parse().and_then(|module| {
+            let info = source.validate(&module, Capabilities::default())?;
+            Ok((module, info))
+        });
+        match compiled {
+            Ok((module, info)) => modules.push((source, module, info)),
+            Err(diagnostics) => errors.extend(diagnostics),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(Target::ALL
+        .into_iter()
+        .map(|target| {
+            let mut report = Report {
+                target,
+                errors: Vec::new(),
+                warnings: Vec::new(),
+            };
+            for (source, module, info) in &modules {
+                report.check(source, module, info);
+            }
+            report
+        })
+        .collect())
+}
+
+impl Report {
+    fn check(&mut self, source: &ShaderSource, module: &naga::Module, info: &ModuleInfo) {
+        let pass = source.pass();
+        let result = match self.target {
+            Target::WebGl2 => {
+                // The downlevel validation reports errors with their location in the shader
+                match source.validate(module, Capabilities::empty()) {
+                    Ok(_) => self.errors.extend(uniform_limits(pass, module)),
+                    Err(diagnostics) => self.errors.extend(diagnostics),
+                }
+                Ok(())
+            }
+            Target::SpirV => spv::write_vec(module, info, &spv::Options::default(), None)
+                .map(drop)
+                .map_err(|error| message(&error)),
+            Target::Msl => msl::write_string(
+                module,
+                info,
+                &msl::Options::default(),
+                &msl::PipelineOptions::default(),
+            )
+            .map(drop)
+            .map_err(|error| message(&error)),
+            Target::Hlsl => {
+                let mut output = String::new();
+                hlsl::Writer::new(&mut output, &hlsl::Options::default())
+                    .write(module, info, None)
+                    .map(drop)
+                    .map_err(|error| message(&error))
+            }
+            Target::GlslEs => {
+                self.warnings.extend(derivative_precision(pass, module));
+                // The vertex stage is the prelude's, which is known to translate
+                module
+                    .entry_points
+                    .iter()
+                    .filter(|entry_point| entry_point.stage == naga::ShaderStage::Fragment)
+                    .try_for_each(|entry_point| {
+                        let mut output = String::new();
+                        glsl::Writer::new(
+                            &mut output,
+                            module,
+                            info,
+                            &glsl::Options {
+                                version: glsl::Version::Embedded {
+                                    version: 300,
+                                    is_webgl: true,
+                                },
+                                ..glsl::Options::default()
+                            },
+                            &glsl::PipelineOptions {
+                                shader_stage: entry_point.stage,
+                                entry_point: entry_point.name.clone(),
+                                multiview: None,
+                            },
+                            naga::proc::BoundsCheckPolicies::default(),
+                        )
+                        .and_then(|mut writer| writer.write())
+                        .map(drop)
+                        .map_err(|error| format!("`{}`: {}", entry_point.name, message(&error)))
+                    })
+            }
+        };
+
+        if let Err(message) = result {
+            self.errors.push(Diagnostic {
+                pass,
+                message,
+                location: None,
+            });
+        }
+    }
+}
+
+/// Uniform buffers larger than WebGL2 guarantees, e.g. from many custom uniforms.
+fn uniform_limits(pass: Pass, module: &naga::Module) -> Vec<Diagnostic> {
+    let max_size = wgpu::Limits::downlevel_webgl2_defaults().max_uniform_buffer_binding_size;
+
+    let mut layouter = naga::proc::Layouter::default();
+    if layouter.update(module.to_ctx()).is_err() {
+        return Vec::new();
+    }
+
+    module
+        .global_variables
+        .iter()
+        .filter(|(_, global)| global.space == AddressSpace::Uniform)
+        .filter(|(_, global)| layouter[global.ty].size > max_size)
+        .map(|(_, global)| Diagnostic {
+            pass,
+            message: format!(
+                "`{}` takes {} bytes, more than the {max_size} bytes a WebGL2 uniform buffer holds",
+                global.name.as_deref().unwrap_or("uniform buffer"),
+                layouter[global.ty].size,
+            ),
+            location: None,
+        })
+        .collect()
+}
+
+/// GLSL ES has no coarse or fine derivatives, they fall back to the default precision.
+fn derivative_precision(pass: Pass, module: &naga::Module) -> Option<Diagnostic> {
+    let functions = module
+        .functions
+        .iter()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter().map(|entry| &entry.function));
+
+    functions
+        .flat_map(|function| function.expressions.iter())
+        .any(|(_, expression)| {
+            matches!(
+                expression,
+                Expression::Derivative {
+                    ctrl: DerivativeControl::Coarse | DerivativeControl::Fine,
+                    ..
+                }
+            )
+        })
+        .then(|| Diagnostic {
+            pass,
+            message: String::from(
+                "coarse and fine derivatives are translated to `dFdx`, `dFdy` and `fwidth`",
+            ),
+            location: None,
+        })
+}
+
+fn message(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(inner) = source {
+        message = format!("{message}: {inner}");
+        source = inner.source();
+    }
+    message
+}
@@ -0,0 +1,205 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use iced::Size;
+use iced_wgpu::wgpu;
+
+/// Large enough for a texel of any color format.
+const SIZE: wgpu::BufferAddress = 16;
+
+/// A pixel of the image pass read back from the GPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pixel {
+    /// Position of the texel, origin at the top left.
+    pub texel: [u32; 2],
+    pub resolution: Size<u32>,
+    /// The color the shader wrote, decoded from sRGB formats.
+    pub color: [f32; 4],
+    /// The color as stored for display, with 8 bits per channel.
+    pub bytes: [u8; 4],
+}
+
+impl Pixel {
+    /// The fragment coordinate of the pixel's center, as the shader sees it.
+    pub fn frag_coord(&self) -> [f32; 2] {
+        self.texel.map(|coordinate| coordinate as f32 + 0.5)
+    }
+
+    pub fn uv(&self) -> [f32; 2] {
+        let [x, y] = self.frag_coord();
+        [
+            x / self.resolution.width as f32,
+            y / self.resolution.height as f32,
+        ]
+    }
+
+    /// The displayed color as `#rrggbbaa`.
+    pub fn hex(&self) -> String {
+        let [r, g, b, a] = self.bytes;
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Copies single texels of the offscreen texture to the CPU for the pixel inspector.
+///
+/// Like the timestamps of [`GpuTimer`](super::timer::GpuTimer), a texel copied in one frame is
+/// read back a few frames later, and no further texels are copied while a copy is in flight.
+pub struct PixelReadback {
+    buffer: wgpu::Buffer,
+    state: State,
+}
+
+enum State {
+    Idle,
+    /// The frame copies the texel to the buffer.
+    Copying([u32; 2]),
+    /// Set once the buffer is mapped.
+    Mapping([u32; 2], Arc<AtomicBool>),
+}
+
+impl PixelReadback {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bulin_canvas.pipeline.pixel_readback"),
+            size: SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            state: State::Idle,
+        }
+    }
+
+    /// Advances the readback of earlier frames, returning the pixel once it is available.
+    ///
+    /// `texel` is copied in the coming frame, unless a copy is still in flight.
+    pub fn prepare(&mut self, texture: &wgpu::Texture, texel: Option<[u32; 2]>) -> Option<Pixel> {
+        let mut pixel = None;
+
+        match &self.state {
+            State::Idle => {}
+            // The last frame was submitted with the copy
+            State::Copying(texel) => {
+                let is_mapped = Arc::new(AtomicBool::new(false));
+                let callback = is_mapped.clone();
+                self.buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        callback.store(result.is_ok(), Ordering::Release);
+                    });
+                self.state = State::Mapping(*texel, is_mapped);
+            }
+            State::Mapping(texel, is_mapped) => {
+                if is_mapped.load(Ordering::Acquire) {
+                    pixel = decode(texture.format(), &self.buffer.slice(..).get_mapped_range())
+                        .map(|(color, bytes)| Pixel {
+                            texel: *texel,
+                            resolution: Size::new(texture.width(), texture.height()),
+                            color,
+                            bytes,
+                        });
+                    self.buffer.unmap();
+                    self.state = State::Idle;
+                }
+            }
+        }
+
+        if let (Some([x, y]), State::Idle) = (texel, &self.state) {
+            self.state = State::Copying([x.min(texture.width() - 1), y.min(texture.height() - 1)]);
+        }
+
+        pixel
+    }
+
+    /// Copies the requested texel of the texture to the buffer.
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        let State::Copying([x, y]) = self.state else {
+            return;
+        };
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// The color and displayed bytes of a texel, `None` for formats the inspector doesn't read.
+fn decode(format: wgpu::TextureFormat, texel: &[u8]) -> Option<([f32; 4], [u8; 4])> {
+    use wgpu::TextureFormat;
+
+    let color: [f32; 4] = match format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => {
+            let mut bytes: [u8; 4] = texel[..4].try_into().ok()?;
+            if matches!(
+                format,
+                TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+            ) {
+                bytes.swap(0, 2);
+            }
+
+            let mut color = bytes.map(|byte| f32::from(byte) / 255.0);
+            if format.is_srgb() {
+                for channel in &mut color[..3] {
+                    *channel = srgb_to_linear(*channel);
+                }
+            }
+            return Some((color, bytes));
+        }
+        TextureFormat::Rgba16Float => {
+            let halves: [u16; 4] = bytemuck::pod_read_unaligned(&texel[..8]);
+            halves.map(f16_to_f32)
+        }
+        TextureFormat::Rgba32Float => bytemuck::pod_read_unaligned(&texel[..16]),
+        _ => return None,
+    };
+
+    // Float formats are shown as they are, clamped to the displayable range
+    Some((
+        color,
+        color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8),
+    ))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f32::from(half & 0x3ff);
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
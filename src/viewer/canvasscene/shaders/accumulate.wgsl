@@ -0,0 +1,42 @@
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2f(vec2u((index << 1) & 2, index & 2));
+    return vec4f(uv * 2. - 1., 0., 1.);
+}
+
+struct Accumulation {
+    // The share of the current frame in the average, 1 over the number of samples
+    weight: f32,
+}
+
+struct Average {
+    // Kept at full precision for the next frame
+    @location(0) average: vec4<f32>,
+    // Shown by the viewer
+    @location(1) image: vec4<f32>,
+}
+
+// The frame the image pass just rendered
+@group(0) @binding(0)
+var current: texture_2d<f32>;
+
+// The average of the frames before it
+@group(0) @binding(1)
+var previous: texture_2d<f32>;
+
+@group(1) @binding(0)
+var<uniform> accumulation: Accumulation;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> Average {
+    let texel = vec2i(position.xy);
+    let color = textureLoad(current, texel, 0);
+
+    // The first sample ignores whatever was averaged before the reset
+    var average = color;
+    if accumulation.weight < 1.0 {
+        average = mix(textureLoad(previous, texel, 0), color, accumulation.weight);
+    }
+
+    return Average(average, average);
+}
@@ -0,0 +1,7 @@
+// The buffer passes, holding the current frame if the buffer is rendered before the sampling pass
+// and the previous frame otherwise, e.g. `texture(sampler2D(buffer_a, buffer_sampler), uv)`
+layout(set = 1, binding = 0) uniform sampler buffer_sampler;
+layout(set = 1, binding = 1) uniform texture2D buffer_a;
+layout(set = 1, binding = 2) uniform texture2D buffer_b;
+layout(set = 1, binding = 3) uniform texture2D buffer_c;
+layout(set = 1, binding = 4) uniform texture2D buffer_d;
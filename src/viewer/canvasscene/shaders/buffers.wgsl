@@ -0,0 +1,7 @@
+// The buffer passes, holding the current frame if the buffer is rendered before the sampling pass
+// and the previous frame otherwise
+@group(1) @binding(0) var buffer_sampler: sampler;
+@group(1) @binding(1) var buffer_a: texture_2d<f32>;
+@group(1) @binding(2) var buffer_b: texture_2d<f32>;
+@group(1) @binding(3) var buffer_c: texture_2d<f32>;
+@group(1) @binding(4) var buffer_d: texture_2d<f32>;
@@ -0,0 +1,12 @@
+// The camera of the viewer, moved by dragging and scrolling while enabled. The ray through a
+// pixel is `transpose(mat3(camera.view)) * normalize(vec3(uv, -1.0 / tan(camera.fov / 2.0)))`,
+// with `uv` its offset from the center in half heights of the image and y up
+layout(set = 0, binding = 1) uniform Camera {
+    // World to view space, looking down -z with y up
+    mat4 view;
+    // View to clip space with depth from 0 to 1
+    mat4 proj;
+    vec3 position;
+    // Vertical field of view in radians
+    float fov;
+} camera;
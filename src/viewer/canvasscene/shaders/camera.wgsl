@@ -0,0 +1,15 @@
+// The camera of the viewer, moved by dragging and scrolling while enabled. The ray through a
+// pixel is `transpose(mat3x3f(camera.view[0].xyz, camera.view[1].xyz, camera.view[2].xyz)) *
+// normalize(vec3f(uv, -1.0 / tan(camera.fov / 2.0)))`, with `uv` its offset from the center in
+// half heights of the image and y up
+struct Camera {
+    // World to view space, looking down -z with y up
+    view: mat4x4<f32>,
+    // View to clip space with depth from 0 to 1
+    proj: mat4x4<f32>,
+    position: vec3<f32>,
+    // Vertical field of view in radians
+    fov: f32,
+}
+
+@group(0) @binding(1) var<uniform> camera: Camera;
@@ -0,0 +1,11 @@
+// The images bound in the channels editor, sampling zero if a channel is empty, e.g.
+// `texture(sampler2D(channel0, channel0_sampler), uv)`. Audio channels are 512x2 with the
+// spectrum at the current time in the first row and the waveform in the second
+layout(set = 2, binding = 0) uniform texture2D channel0;
+layout(set = 2, binding = 1) uniform sampler channel0_sampler;
+layout(set = 2, binding = 2) uniform texture2D channel1;
+layout(set = 2, binding = 3) uniform sampler channel1_sampler;
+layout(set = 2, binding = 4) uniform texture2D channel2;
+layout(set = 2, binding = 5) uniform sampler channel2_sampler;
+layout(set = 2, binding = 6) uniform texture2D channel3;
+layout(set = 2, binding = 7) uniform sampler channel3_sampler;
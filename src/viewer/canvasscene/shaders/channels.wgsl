@@ -0,0 +1,10 @@
+// The images bound in the channels editor, sampling zero if a channel is empty. Audio channels
+// are 512x2 with the spectrum at the current time in the first row and the waveform in the second
+@group(2) @binding(0) var channel0: texture_2d<f32>;
+@group(2) @binding(1) var channel0_sampler: sampler;
+@group(2) @binding(2) var channel1: texture_2d<f32>;
+@group(2) @binding(3) var channel1_sampler: sampler;
+@group(2) @binding(4) var channel2: texture_2d<f32>;
+@group(2) @binding(5) var channel2_sampler: sampler;
+@group(2) @binding(6) var channel3: texture_2d<f32>;
+@group(2) @binding(7) var channel3_sampler: sampler;
@@ -0,0 +1,5 @@
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(0.2, 0.3, 0.2, 1.0);
+}
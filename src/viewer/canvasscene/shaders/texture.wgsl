@@ -8,17 +8,103 @@ fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
     var out: VertexOutput;
     let uv = vec2f(vec2u((index << 1) & 2, index & 2));
     out.position = vec4f(uv * 2. - 1., 0., 1.);
-    out.uv = uv;
+    // Textures have their origin at the top left, like fragment and mouse coordinates
+    out.uv = vec2f(uv.x, 1. - uv.y);
     return out;
 }
 
+// Pan and zoom of the displayed image
+struct View {
+    // The point of the image at the center, from 0 to 1 across it
+    center: vec2<f32>,
+    zoom: f32,
+}
+
+// How the colors are shown
+struct Output {
+    // Multiplies the colors before tone mapping
+    exposure: f32,
+    // 0 none, 1 Reinhard, 2 ACES, 3 filmic
+    tone_mapping: u32,
+    // Whether the image pass wrote sRGB encoded colors rather than linear ones
+    is_encoded: u32,
+    // Whether the target encodes the written colors itself
+    is_target_srgb: u32,
+}
+
 @group(0) @binding(0)
 var tex: texture_2d<f32>;
 
 @group(0) @binding(1)
 var samp: sampler;
 
+@group(1) @binding(0)
+var<uniform> view: View;
+
+@group(2) @binding(0)
+var<uniform> output: Output;
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return select(pow((color + 0.055) / 1.055, vec3f(2.4)), color / 12.92, color <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055, color * 12.92, color <= vec3f(0.0031308));
+}
+
+// Narkowicz, "ACES Filmic Tone Mapping Curve"
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    return (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+}
+
+// Hable, "Uncharted 2: HDR Lighting"
+fn hable(color: vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    return (color * (a * color + c * b) + d * e) / (color * (a * color + b) + d * f) - e / f;
+}
+
+fn filmic(color: vec3<f32>) -> vec3<f32> {
+    let white = 11.2;
+    return hable(2.0 * color) / hable(vec3f(white));
+}
+
+fn tone_map(color: vec3<f32>) -> vec3<f32> {
+    switch output.tone_mapping {
+        case 1u: {
+            return color / (1.0 + color);
+        }
+        case 2u: {
+            return aces(color);
+        }
+        case 3u: {
+            return filmic(color);
+        }
+        default: {
+            return color;
+        }
+    }
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(tex, samp, in.uv);
+    let uv = view.center + (in.uv - 0.5) / view.zoom;
+
+    let color = textureSample(tex, samp, uv);
+
+    var rgb = max(color.rgb, vec3f(0.0));
+    if output.is_encoded != 0u {
+        rgb = srgb_to_linear(rgb);
+    }
+    rgb = clamp(tone_map(rgb * output.exposure), vec3f(0.0), vec3f(1.0));
+    if output.is_target_srgb == 0u {
+        rgb = linear_to_srgb(rgb);
+    }
+
+    let is_inside = all(uv >= vec2f(0.0)) && all(uv <= vec2f(1.0));
+    return select(vec4f(0.0, 0.0, 0.0, 1.0), vec4f(rgb, color.a), is_inside);
 }
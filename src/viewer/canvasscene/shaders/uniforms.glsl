@@ -0,0 +1,30 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    vec2 resolution;
+    // Cursor position in pixels, origin at the top left
+    vec2 mouse;
+    // Where the last click was released
+    vec2 mouse_click;
+    // Where the buttons currently held were first pressed
+    vec2 mouse_drag_start;
+    // Held buttons: 1 left, 2 right, 4 middle
+    uint mouse_buttons;
+    // Seconds since playback started
+    float time;
+    // Seconds between the last two frames
+    float time_delta;
+    // Frames rendered since playback started
+    uint frame;
+    // Year, month, day and seconds since midnight in UTC
+    vec4 date;
+    // Samples per second of sound
+    float sample_rate;
+    // Zoom of the viewer while the shader renders the view, 1 otherwise
+    float view_zoom;
+    // The pixel shown at the center of the viewer, `(position - resolution / 2) / view_zoom +
+    // view_center` zooms into a position
+    vec2 view_center;
+    // Frames averaged into the image before this one while accumulating, 0 otherwise
+    uint sample_index;
+} uniforms;
@@ -1,5 +1,30 @@
 struct Uniforms {
     resolution: vec2<f32>,
+    // Cursor position in pixels, origin at the top left
+    mouse: vec2<f32>,
+    // Where the last click was released
+    mouse_click: vec2<f32>,
+    // Where the buttons currently held were first pressed
+    mouse_drag_start: vec2<f32>,
+    // Held buttons: 1 left, 2 right, 4 middle
+    mouse_buttons: u32,
+    // Seconds since playback started
+    time: f32,
+    // Seconds between the last two frames
+    time_delta: f32,
+    // Frames rendered since playback started
+    frame: u32,
+    // Year, month, day and seconds since midnight in UTC
+    date: vec4<f32>,
+    // Samples per second of sound
+    sample_rate: f32,
+    // Zoom of the viewer while the shader renders the view, 1 otherwise
+    view_zoom: f32,
+    // The pixel shown at the center of the viewer, `(position - resolution / 2) / view_zoom +
+    // view_center` zooms into a position
+    view_center: vec2<f32>,
+    // Frames averaged into the image before this one while accumulating, 0 otherwise
+    sample_index: u32,
 }
 
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
\ No newline at end of file
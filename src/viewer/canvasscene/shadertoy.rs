@@ -0,0 +1,875 @@
+use std::collections::BTreeSet;
+
+use naga::back::glsl;
+use naga::{AddressSpace, Binding, BuiltIn, Expression, Handle, Literal, Span, TypeInner};
+
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::{Language, Pass};
+use crate::uniforms_editor::uniform::{Type, Uniform};
+use crate::util::replace_identifier;
+use crate::viewer::canvasscene::camera::Camera;
+use crate::viewer::canvasscene::source::ShaderSource;
+use crate::viewer::canvasscene::uniforms::CustomUniforms;
+
+/// Shadertoy renders with WebGL 2.
+const VERSION: glsl::Version = glsl::Version::Embedded {
+    version: 300,
+    is_webgl: true,
+};
+
+/// How the members of `struct Uniforms` are filled from Shadertoy's inputs, which have their
+/// origin at the bottom left and count months from zero.
+const UNIFORMS: [&str; 13] = [
+    "    uniforms.resolution = iResolution.xy;",
+    "    uniforms.mouse = vec2(iMouse.x, iResolution.y - iMouse.y);",
+    "    uniforms.mouse_click = uniforms.mouse;",
+    "    uniforms.mouse_drag_start = vec2(abs(iMouse.z), iResolution.y - abs(iMouse.w));",
+    "    uniforms.mouse_buttons = uint(iMouse.z > 0.0);",
+    "    uniforms.time = iTime;",
+    "    uniforms.time_delta = iTimeDelta;",
+    "    uniforms.frame = uint(iFrame);",
+    "    uniforms.date = iDate + vec4(0.0, 1.0, 0.0, 0.0);",
+    "    uniforms.sample_rate = iSampleRate;",
+    "    uniforms.view_zoom = 1.0;",
+    "    uniforms.view_center = iResolution.xy * 0.5;",
+    "    uniforms.sample_index = 0u;",
+];
+
+/// Converts the image pass to GLSL that can be pasted into the image tab of a new Shadertoy.
+///
+/// The built-in uniforms are filled from Shadertoy's inputs and the custom uniforms and the camera
+/// are inlined with their current values. Channels map to `iChannel0` to `iChannel3`, their
+/// samplers have to be configured in Shadertoy.
+pub fn export(
+    shader: &str,
+    uniforms: &[Uniform],
+    camera: &Camera,
+    language: Language,
+) -> Result<String, Vec<Diagnostic>> {
+    let compiled = ShaderSource::new(
+        Pass::Image,
+        shader,
+        &CustomUniforms::new(uniforms),
+        language,
+    )
+    .compile()?;
+    let mut module = compiled.module().clone();
+
+    let entry_point = module
+        .entry_points
+        .iter()
+        .position(|entry_point| entry_point.stage == naga::ShaderStage::Fragment)
+        .expect("Compiled shaders have a fragment entry point");
+    check_interface(&module, &module.entry_points[entry_point].function)?;
+
+    // The built-in and custom uniforms and the camera become globals assigned in `mainImage`
+    for (_, global) in module.global_variables.iter_mut() {
+        if matches!(
+            global.name.as_deref(),
+            Some("uniforms" | "camera" | "customs")
+        ) {
+            global.space = AddressSpace::Private;
+            global.binding = None;
+        }
+    }
+    inline_customs(&mut module, uniforms);
+    // The entry point becomes `mainImage`, e.g. shaders imported from Shadertoy already have one
+    for (_, function) in module.functions.iter_mut() {
+        if function.name.as_deref() == Some("mainImage") {
+            function.name = Some(String::from("image"));
+        }
+    }
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| vec![diagnostic(error.into_inner().to_string())])?;
+
+    // Globals of functions that aren't called are left out of the output
+    let used: Vec<&str> = module
+        .global_variables
+        .iter()
+        .filter(|&(handle, _)| !info.get_entry_point(entry_point)[handle].is_empty())
+        .filter_map(|(_, global)| global.name.as_deref())
+        .collect();
+
+    let buffers: Vec<&str> = used
+        .iter()
+        .copied()
+        .filter(|name| name.starts_with("buffer_") && *name != "buffer_sampler")
+        .collect();
+    if !buffers.is_empty() {
+        return Err(buffers
+            .into_iter()
+            .map(|name| {
+                diagnostic(format!(
+                    "`{name}` has no Shadertoy equivalent, buffer passes have to be set up by hand"
+                ))
+            })
+            .collect());
+    }
+
+    let mut output = String::new();
+    let reflection = glsl::Writer::new(
+        &mut output,
+        &module,
+        &info,
+        &glsl::Options {
+            version: VERSION,
+            writer_flags: glsl::WriterFlags::empty(),
+            ..glsl::Options::default()
+        },
+        &glsl::PipelineOptions {
+            shader_stage: naga::ShaderStage::Fragment,
+            entry_point: module.entry_points[entry_point].name.clone(),
+            multiview: None,
+        },
+        naga::proc::BoundsCheckPolicies::default(),
+    )
+    .and_then(|mut writer| writer.write())
+    .map_err(|error| vec![diagnostic(error.to_string())])?;
+
+    let mut renames = vec![
+        (String::from("_fs2p_location0"), String::from("fragColor")),
+        (
+            String::from("gl_FragCoord"),
+            String::from("vec4(fragCoord.x, iResolution.y - fragCoord.y, 0.0, 1.0)"),
+        ),
+    ];
+    for (name, mapping) in &reflection.texture_mapping {
+        let channel = module.global_variables[mapping.texture]
+            .name
+            .as_deref()
+            .and_then(|name| name.strip_prefix("channel"))
+            .unwrap_or_default();
+        renames.push((name.clone(), format!("iChannel{channel}")));
+    }
+
+    let mut prologue: Vec<String> = Vec::new();
+    if used.contains(&"uniforms") {
+        prologue.extend(UNIFORMS.map(String::from));
+    }
+    if used.contains(&"camera") {
+        prologue.extend(camera_lines(camera));
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in output.lines() {
+        // Shadertoy declares the version, precision, inputs and output itself
+        if ["#version", "precision ", "uniform ", "layout("]
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+            || line.is_empty() && lines.last().is_none_or(String::is_empty)
+        {
+            continue;
+        }
+
+        if line == "void main() {" {
+            lines.push(String::from(
+                "void mainImage(out vec4 fragColor, in vec2 fragCoord) {",
+            ));
+            lines.extend(prologue.iter().cloned());
+        } else {
+            lines.push(renames.iter().fold(line.to_string(), |line, (from, to)| {
+                replace_identifier(&line, from, to)
+            }));
+        }
+    }
+
+    Ok(lines.join("\n").trim_end().to_string() + "\n")
+}
+
+/// Shadertoy's combined samplers expressed with the separate textures and samplers of the prelude.
+const CHANNELS: &str = "#define iChannel0 sampler2D(channel0, channel0_sampler)
+#define iChannel1 sampler2D(channel1, channel1_sampler)
+#define iChannel2 sampler2D(channel2, channel2_sampler)
+#define iChannel3 sampler2D(channel3, channel3_sampler)
+";
+
+/// Shadertoy's inputs expressed with the prelude, defined only when the shader uses them.
+const INPUTS: [(&str, &str); 8] = [
+    (
+        "iResolution",
+        "#define iResolution vec3(uniforms.resolution, 1.0)",
+    ),
+    ("iTime", "#define iTime uniforms.time"),
+    ("iTimeDelta", "#define iTimeDelta uniforms.time_delta"),
+    ("iFrame", "#define iFrame int(uniforms.frame)"),
+    (
+        "iDate",
+        "#define iDate (uniforms.date - vec4(0.0, 1.0, 0.0, 0.0))",
+    ),
+    ("iSampleRate", "#define iSampleRate uniforms.sample_rate"),
+    (
+        "iMouse",
+        "vec4 shadertoy_mouse() {
+    vec2 position = vec2(uniforms.mouse.x, uniforms.resolution.y - uniforms.mouse.y);
+    vec2 start = vec2(uniforms.mouse_drag_start.x, uniforms.resolution.y - uniforms.mouse_drag_start.y);
+    return (uniforms.mouse_buttons & 1u) != 0u ? vec4(position, start) : vec4(position, -start);
+}
+#define iMouse shadertoy_mouse()",
+    ),
+    (
+        "iChannelResolution",
+        "#define iChannelResolution vec3[4](\\
+    vec3(textureSize(iChannel0, 0), 1.0), vec3(textureSize(iChannel1, 0), 1.0), \\
+    vec3(textureSize(iChannel2, 0), 1.0), vec3(textureSize(iChannel3, 0), 1.0))",
+    ),
+];
+
+/// Shadertoy's inputs without an equivalent in bulin and the constants replacing them.
+const UNSUPPORTED_INPUTS: [(&str, &str); 2] = [
+    ("iFrameRate", "60.0"),
+    ("iChannelTime", "float[4](0.0, 0.0, 0.0, 0.0)"),
+];
+
+/// Calls `mainImage` with the fragment coordinate flipped to Shadertoy's bottom left origin.
+const IMPORT_MAIN: &str = "
+layout(location = 0) out vec4 shadertoy_color;
+void main() {
+    mainImage(shadertoy_color, vec2(gl_FragCoord.x, uniforms.resolution.y - gl_FragCoord.y));
+}
+";
+
+/// A Shadertoy shader translated to WGSL, or adapted to bulin's GLSL.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    pub shader: String,
+    /// Features of the shader that were replaced because bulin doesn't support them.
+    pub unsupported: Vec<String>,
+}
+
+/// Translates the image tab of a Shadertoy, a GLSL `mainImage` function, to a shader in the
+/// language.
+///
+/// Shadertoy's inputs are mapped onto the built-in uniforms and `iChannel0` to `iChannel3` onto
+/// the channels. GLSL shaders keep the original code and define the inputs in front of it.
+pub fn import(glsl: &str, language: Language) -> Result<Imported, Vec<String>> {
+    let identifiers = identifiers(glsl);
+    if !identifiers.contains("mainImage") {
+        return Err(vec![String::from("the shader has no `mainImage` function")]);
+    }
+
+    let mut definitions = String::from(CHANNELS);
+    for (input, definition) in INPUTS {
+        if identifiers.contains(input) {
+            definitions.push_str(definition);
+            definitions.push('\n');
+        }
+    }
+
+    let mut unsupported = Vec::new();
+    for (input, constant) in UNSUPPORTED_INPUTS {
+        if identifiers.contains(input) {
+            definitions.push_str(&format!("#define {input} {constant}\n"));
+            unsupported.push(format!(
+                "`{input}` is not available and was replaced with `{constant}`"
+            ));
+        }
+    }
+
+    let shader = format!("{definitions}{glsl}\n{IMPORT_MAIN}");
+
+    // The prelude of GLSL projects without custom uniforms
+    let prelude = format!(
+        "{}\n{}",
+        include_str!("shaders/uniforms.glsl"),
+        include_str!("shaders/channels.glsl"),
+    );
+    let prelude_lines = prelude.lines().count() + definitions.lines().count();
+    let source = prelude + &shader;
+    let location = |span: naga::Span| {
+        let location = span.location(&source);
+        match (location.line_number as usize).checked_sub(prelude_lines) {
+            Some(line) if line > 0 => format!("{line}:{}: ", location.line_position),
+            _ => String::new(),
+        }
+    };
+
+    let module = naga::front::glsl::Frontend::default()
+        .parse(
+            &naga::front::glsl::Options::from(naga::ShaderStage::Fragment),
+            &source,
+        )
+        .map_err(|errors| {
+            errors
+                .errors
+                .into_iter()
+                .map(|error| format!("{}{}", location(error.meta), error.kind))
+                .collect::<Vec<_>>()
+        })?;
+
+    // Uniformity is left to the compilation of the translated shader, which reports it in place
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all() - naga::valid::ValidationFlags::CONTROL_FLOW_UNIFORMITY,
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| {
+        let span = error
+            .spans()
+            .next()
+            .map(|(span, _)| *span)
+            .unwrap_or_default();
+        vec![format!("{}{}", location(span), error.into_inner())]
+    })?;
+
+    let shader = match language {
+        Language::Glsl => shader,
+        Language::Wgsl => strip_prelude(
+            &naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+                .map_err(|error| vec![error.to_string()])?,
+        ),
+    };
+
+    Ok(Imported {
+        shader,
+        unsupported,
+    })
+}
+
+/// Removes the declarations that bulin's prelude provides from the translated shader.
+fn strip_prelude(wgsl: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut skip_block = false;
+    let mut skip_line = false;
+
+    for line in wgsl.lines() {
+        if skip_block {
+            skip_block = line != "}";
+            continue;
+        }
+        if skip_line {
+            skip_line = false;
+            continue;
+        }
+        if line == "struct Uniforms {" || line == "struct Customs {" {
+            skip_block = true;
+            continue;
+        }
+        // Bindings are written on their own line, followed by the variable
+        if line.starts_with("@group(") {
+            skip_line = true;
+            continue;
+        }
+        if line.is_empty() && lines.last().is_none_or(String::is_empty) {
+            continue;
+        }
+
+        // Names ending in a digit get an underscore appended
+        lines.push((0..4).fold(line.to_string(), |line, channel| {
+            replace_identifier(
+                &line,
+                &format!("channel{channel}_"),
+                &format!("channel{channel}"),
+            )
+        }));
+    }
+
+    lines.join("\n").trim_end().to_string() + "\n"
+}
+
+fn identifiers(source: &str) -> BTreeSet<&str> {
+    source
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|identifier| !identifier.is_empty())
+        .collect()
+}
+
+/// Shadertoy only provides the fragment coordinate and a single color output.
+fn check_interface(
+    module: &naga::Module,
+    function: &naga::Function,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+
+    for argument in &function.arguments {
+        if argument.binding != Some(Binding::BuiltIn(BuiltIn::Position { invariant: false })) {
+            errors.push(diagnostic(format!(
+                "input `{}` has no Shadertoy equivalent, only `@builtin(position)` is available",
+                argument.name.as_deref().unwrap_or_default()
+            )));
+        }
+    }
+
+    let outputs: Vec<&Binding> = match &function.result {
+        Some(naga::FunctionResult {
+            binding: Some(binding),
+            ..
+        }) => vec![binding],
+        Some(result) => match &module.types[result.ty].inner {
+            TypeInner::Struct { members, .. } => members
+                .iter()
+                .filter_map(|member| member.binding.as_ref())
+                .collect(),
+            _ => Vec::new(),
+        },
+        None => Vec::new(),
+    };
+    if !matches!(outputs[..], [Binding::Location { location: 0, .. }]) {
+        errors.push(diagnostic(String::from(
+            "the fragment entry point has to return a single `@location(0)` color",
+        )));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// How the members of `struct Camera` are filled with the camera of the project, projecting to
+/// the aspect ratio of Shadertoy's viewport.
+fn camera_lines(camera: &Camera) -> Vec<String> {
+    let matrix = |columns: [[f32; 4]; 4]| {
+        let values: Vec<String> = columns.iter().flatten().map(|v| format!("{v:?}")).collect();
+        format!("mat4({})", values.join(", "))
+    };
+    let [x, y, z] = camera.position();
+
+    vec![
+        format!("    camera.view = {};", matrix(camera.view())),
+        format!("    camera.proj = {};", matrix(camera.projection(1.0))),
+        String::from("    camera.proj[0][0] /= iResolution.x / iResolution.y;"),
+        format!("    camera.position = vec3({x:?}, {y:?}, {z:?});"),
+        format!("    camera.fov = {:?};", camera.fov.to_radians()),
+    ]
+}
+
+/// Initializes the `customs` global with the current values of the custom uniforms.
+fn inline_customs(module: &mut naga::Module, uniforms: &[Uniform]) {
+    let Some((handle, ty)) = module
+        .global_variables
+        .iter()
+        .find(|(_, global)| global.name.as_deref() == Some("customs"))
+        .map(|(handle, global)| (handle, global.ty))
+    else {
+        return;
+    };
+    let TypeInner::Struct { members, .. } = &module.types[ty].inner else {
+        return;
+    };
+
+    let components = members
+        .iter()
+        .zip(uniforms)
+        .map(|(member, uniform)| {
+            let literals: Vec<Handle<Expression>> = literals(&uniform.value)
+                .into_iter()
+                .map(|literal| {
+                    module
+                        .global_expressions
+                        .append(Expression::Literal(literal), Span::UNDEFINED)
+                })
+                .collect();
+
+            match literals[..] {
+                [literal] => literal,
+                _ => module.global_expressions.append(
+                    Expression::Compose {
+                        ty: member.ty,
+                        components: literals,
+                    },
+                    Span::UNDEFINED,
+                ),
+            }
+        })
+        .collect();
+
+    let init = module
+        .global_expressions
+        .append(Expression::Compose { ty, components }, Span::UNDEFINED);
+    module.global_variables[handle].init = Some(init);
+}
+
+fn literals(value: &Type) -> Vec<Literal> {
+    match *value {
+        Type::Int(v) => vec![Literal::I32(v)],
+        Type::Float(v) => vec![Literal::F32(v)],
+        Type::VecFloat2((x, y)) => [x, y].map(Literal::F32).to_vec(),
+        Type::VecFloat3((x, y, z)) | Type::Col3((x, y, z)) => [x, y, z].map(Literal::F32).to_vec(),
+        Type::VecFloat4((x, y, z, w)) | Type::Col4((x, y, z, w)) => {
+            [x, y, z, w].map(Literal::F32).to_vec()
+        }
+        Type::VecInt2((x, y)) => [x, y].map(Literal::I32).to_vec(),
+        Type::VecInt3((x, y, z)) => [x, y, z].map(Literal::I32).to_vec(),
+        Type::VecInt4((x, y, z, w)) => [x, y, z, w].map(Literal::I32).to_vec(),
+    }
+}
+
+fn diagnostic(message: String) -> Diagnostic {
+    Diagnostic {
+        pass: Pass::Image,
+        message,
+        location: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADERTOY: &str = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    fragColor = texture(iChannel0, uv) * sin(iTime);
+}";
+
+    /// Shadertoy's inputs, declared like the GLSL front end of naga accepts them.
+    const SHADERTOY_INPUTS: &str = "#version 450
+layout(set = 0, binding = 0) uniform Inputs {
+    vec3 iResolution;
+    float iTime;
+    float iTimeDelta;
+    float iFrameRate;
+    int iFrame;
+    vec4 iMouse;
+    vec4 iDate;
+    float iSampleRate;
+};
+layout(set = 1, binding = 0) uniform texture2D shadertoy_channel;
+layout(set = 1, binding = 1) uniform sampler shadertoy_sampler;
+#define iChannel0 sampler2D(shadertoy_channel, shadertoy_sampler)
+#define iChannel1 sampler2D(shadertoy_channel, shadertoy_sampler)
+#define iChannel2 sampler2D(shadertoy_channel, shadertoy_sampler)
+#define iChannel3 sampler2D(shadertoy_channel, shadertoy_sampler)
+";
+
+    /// Calls `mainImage` like Shadertoy does.
+    const SHADERTOY_MAIN: &str = "
+layout(location = 0) out vec4 shadertoy_color;
+void main() {
+    mainImage(shadertoy_color, gl_FragCoord.xy);
+}
+";
+
+    /// Checks that exported code is a valid image tab of a Shadertoy.
+    fn is_valid_shadertoy(code: &str) {
+        let source = format!("{SHADERTOY_INPUTS}{code}{SHADERTOY_MAIN}");
+        let module = naga::front::glsl::Frontend::default()
+            .parse(
+                &naga::front::glsl::Options::from(naga::ShaderStage::Fragment),
+                &source,
+            )
+            .unwrap_or_else(|errors| panic!("{code}\n{errors:?}"));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|error| panic!("{code}\n{error:?}"));
+    }
+
+    fn compiles(shader: &str, language: Language) {
+        if let Err(diagnostics) =
+            ShaderSource::new(Pass::Image, shader, &CustomUniforms::new(&[]), language).compile()
+        {
+            panic!("{shader}\n{diagnostics:?}");
+        }
+    }
+
+    #[test]
+    fn import_wraps_main_image_in_glsl() {
+        let imported = import(SHADERTOY, Language::Glsl).unwrap();
+
+        assert!(imported.unsupported.is_empty());
+        assert!(imported
+            .shader
+            .contains("#define iResolution vec3(uniforms.resolution, 1.0)"));
+        assert!(imported
+            .shader
+            .contains("#define iChannel0 sampler2D(channel0, channel0_sampler)"));
+        assert!(imported.shader.contains(SHADERTOY));
+        assert!(imported.shader.contains(
+            "mainImage(shadertoy_color, vec2(gl_FragCoord.x, uniforms.resolution.y - gl_FragCoord.y));"
+        ));
+        // Only the inputs the shader uses are defined
+        assert!(!imported.shader.contains("iMouse"));
+        compiles(&imported.shader, Language::Glsl);
+    }
+
+    #[test]
+    fn import_translates_to_wgsl_using_the_prelude() {
+        let imported = import(SHADERTOY, Language::Wgsl).unwrap();
+
+        assert!(!imported.shader.contains("struct Uniforms"));
+        assert!(!imported.shader.contains("@group("));
+        assert!(imported.shader.contains(".resolution"));
+        assert!(imported.shader.contains(".time"));
+        assert!(imported
+            .shader
+            .contains("textureSample(channel0, channel0_sampler, "));
+        compiles(&imported.shader, Language::Wgsl);
+    }
+
+    #[test]
+    fn import_replaces_unsupported_inputs() {
+        let imported = import(
+            "void mainImage(out vec4 fragColor, in vec2 fragCoord) { fragColor = vec4(iFrameRate); }",
+            Language::Glsl,
+        )
+        .unwrap();
+
+        assert_eq!(imported.unsupported.len(), 1);
+        assert!(imported.shader.contains("#define iFrameRate 60.0"));
+        compiles(&imported.shader, Language::Glsl);
+    }
+
+    #[test]
+    fn import_requires_main_image() {
+        assert!(import("void main() {}", Language::Glsl).is_err());
+    }
+
+    #[test]
+    fn export_maps_uniforms_and_channels_to_shadertoy_inputs() {
+        let shader = "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / uniforms.resolution;
+    return textureSample(channel1, channel1_sampler, uv) * uniforms.time;
+}";
+        let exported = export(shader, &[], &Camera::default(), Language::Wgsl).unwrap();
+
+        assert!(exported.contains("void mainImage(out vec4 fragColor, in vec2 fragCoord) {"));
+        assert!(exported.contains("    uniforms.resolution = iResolution.xy;"));
+        assert!(exported.contains("    uniforms.time = iTime;"));
+        assert!(exported.contains("iChannel1"));
+        assert!(exported.contains("fragColor = "));
+        assert!(exported.contains("vec4(fragCoord.x, iResolution.y - fragCoord.y, 0.0, 1.0)"));
+        is_valid_shadertoy(&exported);
+    }
+
+    #[test]
+    fn export_of_import_round_trips() {
+        for language in Language::ALL {
+            let imported = import(SHADERTOY, language).unwrap();
+            let exported = export(&imported.shader, &[], &Camera::default(), language).unwrap();
+
+            assert!(exported.contains("void mainImage(out vec4 fragColor, in vec2 fragCoord) {"));
+            assert!(exported.contains("iChannel0"));
+            assert!(exported.contains("uniforms.resolution = iResolution.xy;"));
+            // The imported `mainImage` is renamed so it doesn't clash with the entry point
+            assert_eq!(exported.matches("mainImage").count(), 1, "{exported}");
+            is_valid_shadertoy(&exported);
+        }
+    }
+
+    /// The whole export of an image pass written in WGSL.
+    const EXPORTED_WGSL: &str = "struct Uniforms {
+    vec2 resolution;
+    vec2 mouse;
+    vec2 mouse_click;
+    vec2 mouse_drag_start;
+    uint mouse_buttons;
+    float time;
+    float time_delta;
+    uint frame;
+    vec4 date;
+    float sample_rate;
+    float view_zoom;
+    vec2 view_center;
+    uint sample_index;
+};
+struct Camera {
+    mat4x4 view;
+    mat4x4 proj;
+    vec3 position;
+    float fov;
+};
+Uniforms uniforms = Uniforms(vec2(0.0), vec2(0.0), vec2(0.0), vec2(0.0), 0u, 0.0, 0.0, 0u, vec4(0.0), 0.0, 0.0, vec2(0.0), 0u);
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    uniforms.resolution = iResolution.xy;
+    uniforms.mouse = vec2(iMouse.x, iResolution.y - iMouse.y);
+    uniforms.mouse_click = uniforms.mouse;
+    uniforms.mouse_drag_start = vec2(abs(iMouse.z), iResolution.y - abs(iMouse.w));
+    uniforms.mouse_buttons = uint(iMouse.z > 0.0);
+    uniforms.time = iTime;
+    uniforms.time_delta = iTimeDelta;
+    uniforms.frame = uint(iFrame);
+    uniforms.date = iDate + vec4(0.0, 1.0, 0.0, 0.0);
+    uniforms.sample_rate = iSampleRate;
+    uniforms.view_zoom = 1.0;
+    uniforms.view_center = iResolution.xy * 0.5;
+    uniforms.sample_index = 0u;
+    vec4 position = vec4(fragCoord.x, iResolution.y - fragCoord.y, 0.0, 1.0);
+    vec2 _e4 = uniforms.resolution;
+    vec2 uv = (position.xy / _e4);
+    vec4 _e8 = texture(iChannel0, vec2(uv));
+    float _e11 = uniforms.time;
+    fragColor = (_e8 * sin(_e11));
+    return;
+}
+";
+
+    /// The whole export of an image pass written in GLSL, which goes through naga's GLSL front end
+    /// and keeps its `main`.
+    const EXPORTED_GLSL: &str = "struct Uniforms {
+    vec2 resolution;
+    vec2 mouse;
+    vec2 mouse_click;
+    vec2 mouse_drag_start;
+    uint mouse_buttons;
+    float time;
+    float time_delta;
+    uint frame;
+    vec4 date;
+    float sample_rate;
+    float view_zoom;
+    vec2 view_center;
+    uint sample_index;
+};
+struct Camera {
+    mat4x4 view;
+    mat4x4 proj;
+    vec3 position;
+    float fov;
+};
+struct type_30 {
+    vec4 color;
+};
+Uniforms uniforms = Uniforms(vec2(0.0), vec2(0.0), vec2(0.0), vec2(0.0), 0u, 0.0, 0.0, 0u, vec4(0.0), 0.0, 0.0, vec2(0.0), 0u);
+
+vec4 color = vec4(0.0);
+
+vec4 gen_gl_FragCoord_1 = vec4(0.0);
+
+void main_1() {
+    vec2 uv = vec2(0.0);
+    vec4 _e51 = gen_gl_FragCoord_1;
+    Uniforms _e53 = uniforms;
+    uv = (_e51.xy / _e53.resolution);
+    vec2 _e57 = uv;
+    vec4 _e58 = texture(iChannel0, vec2(_e57));
+    Uniforms _e59 = uniforms;
+    color = (_e58 * sin(_e59.time));
+    return;
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    uniforms.resolution = iResolution.xy;
+    uniforms.mouse = vec2(iMouse.x, iResolution.y - iMouse.y);
+    uniforms.mouse_click = uniforms.mouse;
+    uniforms.mouse_drag_start = vec2(abs(iMouse.z), iResolution.y - abs(iMouse.w));
+    uniforms.mouse_buttons = uint(iMouse.z > 0.0);
+    uniforms.time = iTime;
+    uniforms.time_delta = iTimeDelta;
+    uniforms.frame = uint(iFrame);
+    uniforms.date = iDate + vec4(0.0, 1.0, 0.0, 0.0);
+    uniforms.sample_rate = iSampleRate;
+    uniforms.view_zoom = 1.0;
+    uniforms.view_center = iResolution.xy * 0.5;
+    uniforms.sample_index = 0u;
+    vec4 gen_gl_FragCoord = vec4(fragCoord.x, iResolution.y - fragCoord.y, 0.0, 1.0);
+    gen_gl_FragCoord_1 = gen_gl_FragCoord;
+    main_1();
+    vec4 _e67 = color;
+    type_30 _tmp_return = type_30(_e67);
+    fragColor = _tmp_return.color;
+    return;
+}
+";
+
+    /// The whole export of an image pass reading the camera and a custom uniform.
+    const EXPORTED_CAMERA_AND_CUSTOMS: &str = "struct Uniforms {
+    vec2 resolution;
+    vec2 mouse;
+    vec2 mouse_click;
+    vec2 mouse_drag_start;
+    uint mouse_buttons;
+    float time;
+    float time_delta;
+    uint frame;
+    vec4 date;
+    float sample_rate;
+    float view_zoom;
+    vec2 view_center;
+    uint sample_index;
+};
+struct Camera {
+    mat4x4 view;
+    mat4x4 proj;
+    vec3 position;
+    float fov;
+};
+struct Customs {
+    float gain;
+};
+Camera camera = Camera(mat4x4(0.0), mat4x4(0.0), vec3(0.0), 0.0);
+
+Customs customs = Customs(0.5);
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    camera.view = mat4(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -0.0, -0.0, -4.0, 1.0);
+    camera.proj = mat4(1.7320507, 0.0, 0.0, 0.0, 0.0, 1.7320507, 0.0, 0.0, 0.0, 0.0, -1.00001, -1.0, 0.0, 0.0, -0.0100001, 0.0);
+    camera.proj[0][0] /= iResolution.x / iResolution.y;
+    camera.position = vec3(0.0, 0.0, 4.0);
+    camera.fov = 1.0471976;
+    vec4 position = vec4(fragCoord.x, iResolution.y - fragCoord.y, 0.0, 1.0);
+    vec3 _e3 = camera.position;
+    float _e6 = customs.gain;
+    float _e10 = camera.fov;
+    fragColor = vec4((_e3 * _e6), _e10);
+    return;
+}
+";
+
+    #[test]
+    fn exports_wgsl_image_pass() {
+        let shader = "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / uniforms.resolution;
+    return textureSample(channel0, channel0_sampler, uv) * sin(uniforms.time);
+}";
+        let exported = export(shader, &[], &Camera::default(), Language::Wgsl).unwrap();
+
+        assert_eq!(exported, EXPORTED_WGSL);
+        is_valid_shadertoy(&exported);
+    }
+
+    #[test]
+    fn exports_glsl_image_pass() {
+        let shader = "layout(location = 0) out vec4 color;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / uniforms.resolution;
+    color = texture(sampler2D(channel0, channel0_sampler), uv) * sin(uniforms.time);
+}";
+        let exported = export(shader, &[], &Camera::default(), Language::Glsl).unwrap();
+
+        assert_eq!(exported, EXPORTED_GLSL);
+        is_valid_shadertoy(&exported);
+    }
+
+    #[test]
+    fn exports_image_pass_with_camera_and_custom_uniforms() {
+        let shader = "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4(camera.position * customs.gain, camera.fov);
+}";
+        let uniforms = [Uniform {
+            value: Type::Float(0.5),
+            name: String::from("gain"),
+        }];
+        let exported = export(shader, &uniforms, &Camera::default(), Language::Wgsl).unwrap();
+
+        assert_eq!(exported, EXPORTED_CAMERA_AND_CUSTOMS);
+        is_valid_shadertoy(&exported);
+    }
+
+    #[test]
+    fn export_rejects_reading_buffer_passes() {
+        let shader = "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(buffer_a, vec2<i32>(position.xy), 0);
+}";
+        let diagnostics = export(shader, &[], &Camera::default(), Language::Wgsl).unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`buffer_a` has no Shadertoy equivalent, buffer passes have to be set up by hand"
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use std::borrow::Cow;
+use std::sync::mpsc;
+
+use iced::Size;
+use iced_wgpu::wgpu;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{ResourceBinding, Scalar, TypeInner, VectorSize};
+
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::{Pass, ShaderUpdate};
+use crate::viewer::canvasscene::clock::Clock;
+use crate::viewer::canvasscene::headless::{self, Error};
+use crate::viewer::canvasscene::mouse::Mouse;
+use crate::viewer::canvasscene::source::CompiledShader;
+use crate::viewer::canvasscene::uniforms::{CameraUniforms, DefaultUniforms};
+use crate::viewer::canvasscene::view::View;
+use crate::viewer::canvasscene::CanvasScene;
+
+/// Matches `@workgroup_size` of [`ENTRY_POINT`].
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Samples computed per dispatch, long sounds are split to stay within the limits of the device.
+const CHUNK_SAMPLES: u32 = WORKGROUP_SIZE * 4096;
+
+/// Size of a stereo sample in the storage buffer.
+const SAMPLE_SIZE: u64 = std::mem::size_of::<[f32; 2]>() as u64;
+
+/// Appended to the image pass, calls `sound` once for every sample of a chunk.
+const ENTRY_POINT: &str = "
+struct SoundChunk {
+    // Index of the first sample of the chunk
+    offset: u32,
+    // Samples per second
+    rate: f32,
+}
+
+@group(0) @binding(2) var<storage, read_write> sound_samples: array<vec2<f32>>;
+@group(0) @binding(3) var<uniform> sound_chunk: SoundChunk;
+
+@compute @workgroup_size(64)
+fn sound_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x < arrayLength(&sound_samples) {
+        sound_samples[id.x] = sound(f32(sound_chunk.offset + id.x) / sound_chunk.rate);
+    }
+}
+";
+
+/// Mirrors `struct SoundChunk` of [`ENTRY_POINT`].
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct Chunk {
+    offset: u32,
+    rate: f32,
+}
+
+/// Evaluates `fn sound(time: f32) -> vec2<f32>` of the image pass for the first samples of the
+/// scene described by the updates, returning the left and right channel of each sample.
+///
+/// `sound` sees the built-in uniforms at the start of playback, the camera and the custom
+/// uniforms, but neither the buffers nor the channels.
+pub async fn render(
+    updates: Vec<ShaderUpdate>,
+    samples: u32,
+    sample_rate: u32,
+) -> Result<Vec<[f32; 2]>, Error> {
+    let (mut scene, _) = CanvasScene::new(String::new());
+    headless::apply(&mut scene, updates)?;
+    let image = &scene.active[&Pass::Image];
+    let module = module(&image.compiled).map_err(Error::Shader)?;
+
+    let (device, queue) = headless::request_device().await?;
+
+    let buffer = |label, size, usage| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    };
+    let uniform_usage = wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
+
+    let uniforms = buffer(
+        "bulin_sound.uniforms",
+        std::mem::size_of::<DefaultUniforms>() as u64,
+        uniform_usage,
+    );
+    let camera = buffer(
+        "bulin_sound.camera",
+        std::mem::size_of::<CameraUniforms>() as u64,
+        uniform_usage,
+    );
+    let chunk = buffer(
+        "bulin_sound.chunk",
+        std::mem::size_of::<Chunk>() as u64,
+        uniform_usage,
+    );
+    let output = buffer(
+        "bulin_sound.samples",
+        u64::from(CHUNK_SAMPLES) * SAMPLE_SIZE,
+        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    );
+    let readback = buffer(
+        "bulin_sound.readback",
+        u64::from(CHUNK_SAMPLES) * SAMPLE_SIZE,
+        wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+    );
+    let customs = (!image.custom_uniforms.is_empty()).then(|| {
+        buffer(
+            "bulin_sound.customs",
+            image.custom_uniforms.bytes().len() as u64,
+            uniform_usage,
+        )
+    });
+
+    // The sound starts with playback, regardless of the size it's shown at
+    let size = Size::new(1, 1);
+    let mut default_uniforms = DefaultUniforms::new(
+        size,
+        &Mouse::default(),
+        &Clock::default(),
+        &View::default(),
+        1.0,
+    );
+    default_uniforms.sample_rate = sample_rate as f32;
+    queue.write_buffer(&uniforms, 0, bytemuck::bytes_of(&default_uniforms));
+    queue.write_buffer(
+        &camera,
+        0,
+        bytemuck::bytes_of(&CameraUniforms::new(&scene.camera, size)),
+    );
+    if let Some(customs) = &customs {
+        queue.write_buffer(customs, 0, image.custom_uniforms.bytes());
+    }
+
+    let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let uniform = wgpu::BufferBindingType::Uniform;
+    let storage = wgpu::BufferBindingType::Storage { read_only: false };
+
+    let layout = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries,
+        })
+    };
+    let bind_group = |label, layout, buffers: &[&wgpu::Buffer]| {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .zip(0..)
+            .map(|(buffer, binding)| wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &entries,
+        })
+    };
+
+    let built_ins_layout = layout(
+        "bulin_sound.uniforms_bind_group_layout",
+        &[
+            entry(0, uniform),
+            entry(1, uniform),
+            entry(2, storage),
+            entry(3, uniform),
+        ],
+    );
+    let built_ins = bind_group(
+        "bulin_sound.uniforms_bind_group",
+        &built_ins_layout,
+        &[&uniforms, &camera, &output, &chunk],
+    );
+    // The groups of the buffers and channels stay empty
+    let empty_layout = layout("bulin_sound.empty_bind_group_layout", &[]);
+    let empty = bind_group("bulin_sound.empty_bind_group", &empty_layout, &[]);
+    let customs_layout = layout(
+        "bulin_sound.customs_bind_group_layout",
+        &[entry(0, uniform)],
+    );
+    let customs = customs.as_ref().map(|customs| {
+        bind_group(
+            "bulin_sound.customs_bind_group",
+            &customs_layout,
+            &[customs],
+        )
+    });
+
+    let bind_group_layouts: Vec<&wgpu::BindGroupLayout> =
+        [&built_ins_layout, &empty_layout, &empty_layout]
+            .into_iter()
+            .chain(customs.as_ref().map(|_| &customs_layout))
+            .collect();
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("bulin_sound.pipeline_layout"),
+        bind_group_layouts: &bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("bulin_sound.shader"),
+        source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("bulin_sound.pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader_module,
+        entry_point: Some("sound_main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+
+    let mut result = Vec::with_capacity(samples as usize);
+    for offset in (0..samples).step_by(CHUNK_SAMPLES as usize) {
+        let count = CHUNK_SAMPLES.min(samples - offset);
+        queue.write_buffer(
+            &chunk,
+            0,
+            bytemuck::bytes_of(&Chunk {
+                offset,
+                rate: sample_rate as f32,
+            }),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("bulin_sound.encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("bulin_sound.pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &built_ins, &[]);
+            pass.set_bind_group(1, &empty, &[]);
+            pass.set_bind_group(2, &empty, &[]);
+            if let Some(customs) = &customs {
+                pass.set_bind_group(3, customs, &[]);
+            }
+            pass.dispatch_workgroups(count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        let size = u64::from(count) * SAMPLE_SIZE;
+        encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, size);
+        queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..size);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("Buffer mapping callback was dropped")?;
+
+        result.extend(
+            slice
+                .get_mapped_range()
+                .chunks_exact(SAMPLE_SIZE as usize)
+                .map(bytemuck::pod_read_unaligned::<[f32; 2]>),
+        );
+        readback.unmap();
+    }
+
+    Ok(result)
+}
+
+/// The image pass with a compute entry point calling its `sound` function.
+fn module(shader: &CompiledShader) -> Result<naga::Module, Vec<Diagnostic>> {
+    let module = shader.module();
+    let info = Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(module)
+        .map_err(|error| vec![diagnostic(error.into_inner().to_string())])?;
+
+    let is_vec2 = |ty| {
+        matches!(
+            module.types[ty].inner,
+            TypeInner::Vector {
+                size: VectorSize::Bi,
+                scalar: Scalar::F32,
+            }
+        )
+    };
+    let Some((handle, _)) = module.functions.iter().find(|(_, function)| {
+        function.name.as_deref() == Some("sound")
+            && matches!(
+                function.arguments[..],
+                [naga::FunctionArgument { ty, .. }]
+                    if module.types[ty].inner == TypeInner::Scalar(Scalar::F32)
+            )
+            && function
+                .result
+                .as_ref()
+                .is_some_and(|result| is_vec2(result.ty))
+    }) else {
+        return Err(vec![diagnostic(String::from(
+            "the image pass has no `fn sound(time: f32) -> vec2<f32>`",
+        ))]);
+    };
+
+    // Only group 0 and the custom uniforms are bound for the compute pass
+    let unbound: Vec<Diagnostic> = module
+        .global_variables
+        .iter()
+        .filter(|&(global, _)| !info[handle][global].is_empty())
+        .filter(|(_, global)| matches!(global.binding, Some(ResourceBinding { group: 1 | 2, .. })))
+        .map(|(_, global)| {
+            diagnostic(format!(
+                "`sound` can't read `{}`, buffers and channels aren't bound when rendering sound",
+                global.name.as_deref().unwrap_or_default()
+            ))
+        })
+        .collect();
+    if !unbound.is_empty() {
+        return Err(unbound);
+    }
+
+    // GLSL projects go through WGSL too, so the entry point can be appended as source
+    let source =
+        naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|error| vec![diagnostic(error.to_string())])?
+            + ENTRY_POINT;
+    let module = naga::front::wgsl::parse_str(&source)
+        .map_err(|error| vec![diagnostic(error.message().to_string())])?;
+    Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .map_err(|error| vec![diagnostic(error.into_inner().to_string())])?;
+
+    Ok(module)
+}
+
+fn diagnostic(message: String) -> Diagnostic {
+    Diagnostic {
+        pass: Pass::Image,
+        message,
+        location: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shader_update::Language;
+    use crate::viewer::canvasscene::source::ShaderSource;
+    use crate::viewer::canvasscene::uniforms::CustomUniforms;
+
+    fn compile(shader: &str, language: Language) -> CompiledShader {
+        ShaderSource::new(Pass::Image, shader, &CustomUniforms::new(&[]), language)
+            .compile()
+            .unwrap()
+    }
+
+    fn assert_has_sound_entry_point(module: &naga::Module) {
+        let entry_point = module
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.name == "sound_main")
+            .unwrap();
+        assert_eq!(entry_point.stage, naga::ShaderStage::Compute);
+        assert_eq!(entry_point.workgroup_size, [WORKGROUP_SIZE, 1, 1]);
+
+        let binding = |name| {
+            module
+                .global_variables
+                .iter()
+                .find(|(_, global)| global.name.as_deref() == Some(name))
+                .and_then(|(_, global)| global.binding.clone())
+        };
+        assert_eq!(
+            binding("sound_samples"),
+            Some(ResourceBinding {
+                group: 0,
+                binding: 2
+            })
+        );
+        assert_eq!(
+            binding("sound_chunk"),
+            Some(ResourceBinding {
+                group: 0,
+                binding: 3
+            })
+        );
+    }
+
+    #[test]
+    fn appends_entry_point_to_wgsl() {
+        let compiled = compile(
+            "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4(1.0);
+}
+
+fn sound(time: f32) -> vec2<f32> {
+    return vec2(sin(6.2831 * 440.0 * time)) * uniforms.sample_rate;
+}",
+            Language::Wgsl,
+        );
+        let module = module(&compiled).unwrap();
+
+        assert_has_sound_entry_point(&module);
+        // The image pass is still there
+        assert!(module
+            .entry_points
+            .iter()
+            .any(|entry_point| entry_point.stage == naga::ShaderStage::Fragment));
+    }
+
+    #[test]
+    fn appends_entry_point_to_glsl() {
+        let compiled = compile(
+            "layout(location = 0) out vec4 color;
+
+vec2 sound(float time) {
+    return vec2(sin(6.2831 * 440.0 * time), 0.0);
+}
+
+void main() {
+    color = vec4(1.0);
+}",
+            Language::Glsl,
+        );
+
+        assert_has_sound_entry_point(&module(&compiled).unwrap());
+    }
+
+    #[test]
+    fn requires_sound_function() {
+        let compiled = compile(
+            "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4(1.0);
+}
+
+fn sound(time: f32) -> f32 {
+    return time;
+}",
+            Language::Wgsl,
+        );
+        assert!(module(&compiled).is_err());
+    }
+
+    #[test]
+    fn rejects_channels_in_sound() {
+        let compiled = compile(
+            "@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4(1.0);
+}
+
+fn sound(time: f32) -> vec2<f32> {
+    return textureLoad(channel0, vec2(0), 0).xy;
+}",
+            Language::Wgsl,
+        );
+        let diagnostics = module(&compiled).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("`channel0`"));
+    }
+}
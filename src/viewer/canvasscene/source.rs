@@ -0,0 +1,315 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+
+use crate::diagnostics::{Diagnostic, Location};
+use crate::shader_update::{Language, Pass};
+use crate::viewer::canvasscene::uniforms::CustomUniforms;
+
+/// GLSL sources hold a single stage, so the vertex shader of GLSL projects is a module of its own.
+static GLSL_VERTEX_SHADER: Lazy<Arc<naga::Module>> = Lazy::new(|| {
+    let module = naga::front::glsl::Frontend::default()
+        .parse(
+            &naga::front::glsl::Options::from(naga::ShaderStage::Vertex),
+            include_str!("shaders/vertex_shader.glsl"),
+        )
+        .expect("Parse GLSL vertex shader");
+    Arc::new(module)
+});
+
+/// The declarations prepended to the shaders of every pass.
+pub fn prelude(custom_uniforms: &CustomUniforms, language: Language) -> String {
+    match language {
+        Language::Wgsl => format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n",
+            include_str!("shaders/uniforms.wgsl"),
+            include_str!("shaders/camera.wgsl"),
+            include_str!("shaders/buffers.wgsl"),
+            include_str!("shaders/channels.wgsl"),
+            custom_uniforms.declaration(),
+            include_str!("shaders/vertex_shader.wgsl"),
+        ),
+        Language::Glsl => format!(
+            "{}\n{}\n{}\n{}\n{}\n",
+            include_str!("shaders/uniforms.glsl"),
+            include_str!("shaders/camera.glsl"),
+            include_str!("shaders/buffers.glsl"),
+            include_str!("shaders/channels.glsl"),
+            custom_uniforms.glsl_declaration(),
+        ),
+    }
+}
+
+/// The user shader with the injected prelude of uniforms and vertex shader prepended.
+#[derive(Debug)]
+pub struct ShaderSource {
+    pass: Pass,
+    language: Language,
+    text: String,
+    prelude_lines: usize,
+    declaration: String,
+}
+
+impl ShaderSource {
+    pub fn new(
+        pass: Pass,
+        shader: &str,
+        custom_uniforms: &CustomUniforms,
+        language: Language,
+    ) -> Self {
+        let prelude = prelude(custom_uniforms, language);
+
+        Self {
+            pass,
+            language,
+            prelude_lines: prelude.lines().count(),
+            text: prelude + shader,
+            declaration: custom_uniforms.declaration().to_string(),
+        }
+    }
+
+    pub fn pass(&self) -> Pass {
+        self.pass
+    }
+
+    /// Parses and validates the shader, reporting errors relative to the user shader.
+    pub fn compile(self) -> Result<CompiledShader, Vec<Diagnostic>> {
+        let module = self.parse()?;
+        self.validate(&module, naga::valid::Capabilities::default())?;
+
+        if !module
+            .entry_points
+            .iter()
+            .any(|entry_point| entry_point.stage == naga::ShaderStage::Fragment)
+        {
+            return Err(vec![Diagnostic {
+                pass: self.pass,
+                message: String::from("missing `@fragment` entry point"),
+                location: None,
+            }]);
+        }
+
+        Ok(CompiledShader {
+            pass: self.pass,
+            module: Arc::new(module),
+            vertex_module: (self.language == Language::Glsl).then(|| GLSL_VERTEX_SHADER.clone()),
+            declaration: self.declaration,
+        })
+    }
+
+    pub fn parse(&self) -> Result<naga::Module, Vec<Diagnostic>> {
+        match self.language {
+            Language::Wgsl => naga::front::wgsl::parse_str(&self.text).map_err(|error| {
+                vec![self.diagnostic(error.message().to_string(), error.location(&self.text))]
+            }),
+            Language::Glsl => naga::front::glsl::Frontend::default()
+                .parse(
+                    &naga::front::glsl::Options::from(naga::ShaderStage::Fragment),
+                    &self.text,
+                )
+                .map_err(|errors| {
+                    errors
+                        .errors
+                        .into_iter()
+                        .map(|error| {
+                            self.diagnostic(
+                                error.kind.to_string(),
+                                Some(error.meta.location(&self.text)),
+                            )
+                        })
+                        .collect()
+                }),
+        }
+    }
+
+    /// Validates a module parsed from this source for a device with the given capabilities.
+    pub fn validate(
+        &self,
+        module: &naga::Module,
+        capabilities: naga::valid::Capabilities,
+    ) -> Result<naga::valid::ModuleInfo, Vec<Diagnostic>> {
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(module)
+            .map_err(|error| {
+                let mut message = error.as_inner().to_string();
+                let mut source = error.as_inner().source();
+                while let Some(inner) = source {
+                    message = format!("{message}: {inner}");
+                    source = inner.source();
+                }
+
+                vec![self.diagnostic(message, error.location(&self.text))]
+            })
+    }
+
+    fn diagnostic(&self, message: String, location: Option<naga::SourceLocation>) -> Diagnostic {
+        let location = location.and_then(|location| {
+            let line = (location.line_number as usize).checked_sub(self.prelude_lines)?;
+
+            (line > 0).then_some(Location {
+                line,
+                column: location.line_position as usize,
+            })
+        });
+
+        Diagnostic {
+            pass: self.pass,
+            message,
+            location,
+        }
+    }
+}
+
+/// A validated shader module, ready to be handed to the GPU.
+#[derive(Debug, Clone)]
+pub struct CompiledShader {
+    pass: Pass,
+    module: Arc<naga::Module>,
+    /// The module of the vertex stage if `module` doesn't contain it.
+    vertex_module: Option<Arc<naga::Module>>,
+    declaration: String,
+}
+
+impl CompiledShader {
+    pub fn pass(&self) -> Pass {
+        self.pass
+    }
+
+    pub fn module(&self) -> &naga::Module {
+        &self.module
+    }
+
+    pub fn vertex_module(&self) -> Option<&naga::Module> {
+        self.vertex_module.as_deref()
+    }
+
+    /// The declaration of the custom uniforms this shader was composed with.
+    pub fn declaration(&self) -> &str {
+        &self.declaration
+    }
+}
+
+/// The shader rendered for a pass together with the custom uniforms matching its layout.
+#[derive(Debug, Clone)]
+pub struct ActiveShader {
+    pub version: usize,
+    pub compiled: Arc<CompiledShader>,
+    pub custom_uniforms: Arc<CustomUniforms>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::uniforms_editor::uniform::{Type, Uniform};
+
+    const WGSL: &str = "@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4(1.0) + ;
+}
+";
+
+    const GLSL: &str = "layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(1.0) + ;
+}
+";
+
+    fn location(source: ShaderSource) -> Option<Location> {
+        let diagnostics = source.compile().unwrap_err();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        diagnostics[0].location
+    }
+
+    #[test]
+    fn maps_wgsl_errors_to_shader_lines() {
+        let source = ShaderSource::new(
+            Pass::Image,
+            WGSL,
+            &CustomUniforms::default(),
+            Language::Wgsl,
+        );
+
+        assert_eq!(
+            location(source),
+            Some(Location {
+                line: 3,
+                column: 24
+            })
+        );
+    }
+
+    #[test]
+    fn maps_glsl_errors_to_shader_lines() {
+        let source = ShaderSource::new(
+            Pass::Image,
+            GLSL,
+            &CustomUniforms::default(),
+            Language::Glsl,
+        );
+
+        assert_eq!(
+            location(source),
+            Some(Location {
+                line: 4,
+                column: 25
+            })
+        );
+    }
+
+    #[test]
+    fn maps_errors_past_custom_uniforms_to_shader_lines() {
+        let custom_uniforms = CustomUniforms::new(&[
+            Uniform {
+                value: Type::Float(1.0),
+                name: String::from("gain"),
+            },
+            Uniform {
+                value: Type::VecFloat3((0.0, 0.0, 0.0)),
+                name: String::from("offset"),
+            },
+        ]);
+
+        for (shader, language, line) in [(WGSL, Language::Wgsl, 3), (GLSL, Language::Glsl, 4)] {
+            let source = ShaderSource::new(Pass::Image, shader, &custom_uniforms, language);
+
+            assert_eq!(location(source).map(|location| location.line), Some(line));
+        }
+    }
+
+    #[test]
+    fn drops_locations_in_the_prelude() {
+        let source = ShaderSource::new(
+            Pass::BufferA,
+            WGSL,
+            &CustomUniforms::default(),
+            Language::Wgsl,
+        );
+        let at_line = |line_number| naga::SourceLocation {
+            line_number,
+            line_position: 1,
+            offset: 0,
+            length: 1,
+        };
+
+        let in_prelude = source.diagnostic(String::new(), Some(at_line(1)));
+        assert_eq!(in_prelude.pass, Pass::BufferA);
+        assert_eq!(in_prelude.location, None);
+
+        let last_of_prelude = source.prelude_lines as u32;
+        assert_eq!(
+            source
+                .diagnostic(String::new(), Some(at_line(last_of_prelude)))
+                .location,
+            None
+        );
+        assert_eq!(
+            source
+                .diagnostic(String::new(), Some(at_line(last_of_prelude + 1)))
+                .location,
+            Some(Location { line: 1, column: 1 })
+        );
+    }
+}
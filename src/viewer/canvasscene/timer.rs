@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced_wgpu::wgpu;
+
+/// Size of the two timestamps at the beginning and end of the offscreen passes.
+const SIZE: wgpu::BufferAddress = 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+
+/// The times measured while rendering, written by the primitives and read by the viewer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    /// Time spent on the CPU to prepare the last frame.
+    pub cpu: Option<Duration>,
+    /// Time the GPU took to execute the offscreen passes of a recent frame.
+    pub gpu: Option<Duration>,
+    /// Whether the device was created with timestamp queries, without them only the CPU time and
+    /// the frame rate are measured.
+    pub has_timestamps: bool,
+}
+
+/// Measures the offscreen passes with timestamp queries.
+///
+/// The queries need a device created with [`wgpu::Features::TIMESTAMP_QUERY`]. The viewer renders
+/// with the device of iced's compositor, which doesn't request the feature, so there the timer is
+/// usually unavailable and the overlay shows the CPU time alone.
+///
+/// The timestamps of a frame are read back a few frames later, while no copy is in flight the
+/// passes of new frames aren't measured.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    state: State,
+}
+
+enum State {
+    Idle,
+    /// The frame records the timestamps and copies them to the readback buffer.
+    Recording,
+    /// Set once the readback buffer is mapped.
+    Mapping(Arc<AtomicBool>),
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device doesn't support timestamp queries.
+    pub fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("bulin_canvas.pipeline.timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bulin_canvas.pipeline.timestamps_resolve"),
+            size: SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bulin_canvas.pipeline.timestamps_readback"),
+            size: SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            state: State::Idle,
+        })
+    }
+
+    /// Advances the readback of earlier frames, returning the GPU time once it is available.
+    ///
+    /// `rerender` tells if the offscreen passes of the coming frame are executed.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, rerender: bool) -> Option<Duration> {
+        let mut measured = None;
+
+        match &self.state {
+            State::Idle => {}
+            // The last frame was submitted with the copy
+            State::Recording => {
+                let is_mapped = Arc::new(AtomicBool::new(false));
+                let callback = is_mapped.clone();
+                self.readback_buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        callback.store(result.is_ok(), Ordering::Release);
+                    });
+                self.state = State::Mapping(is_mapped);
+            }
+            State::Mapping(is_mapped) => {
+                if is_mapped.load(Ordering::Acquire) {
+                    let timestamps: [u64; 2] = bytemuck::pod_read_unaligned(
+                        &self.readback_buffer.slice(..).get_mapped_range(),
+                    );
+                    self.readback_buffer.unmap();
+
+                    let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                    let nanos = ticks as f64 * f64::from(queue.get_timestamp_period());
+                    measured = Some(Duration::from_nanos(nanos as u64));
+                    self.state = State::Idle;
+                }
+            }
+        }
+
+        if rerender && matches!(self.state, State::Idle) {
+            self.state = State::Recording;
+        }
+
+        measured
+    }
+
+    /// The timestamp writes of an offscreen pass, if the frame is measured.
+    pub fn timestamp_writes(
+        &self,
+        is_first: bool,
+        is_last: bool,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        (matches!(self.state, State::Recording) && (is_first || is_last)).then(|| {
+            wgpu::RenderPassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: is_first.then_some(0),
+                end_of_pass_write_index: is_last.then_some(1),
+            }
+        })
+    }
+
+    /// Copies the timestamps written by the offscreen passes to the readback buffer.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if matches!(self.state, State::Recording) {
+            encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, SIZE);
+        }
+    }
+}
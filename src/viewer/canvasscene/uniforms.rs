@@ -1,20 +1,333 @@
+use iced::{Point, Size};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::std::{SystemTime, UNIX_EPOCH};
+
+use crate::uniforms_editor::uniform::Uniform;
+use crate::viewer::canvasscene::camera::Camera;
+use crate::viewer::canvasscene::clock::Clock;
+use crate::viewer::canvasscene::mouse::Mouse;
+use crate::viewer::canvasscene::view::View;
+
+/// Samples per second of the `sample_rate` uniform.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Mirrors `struct Uniforms` in `shaders/uniforms.wgsl`, all positions in physical pixels.
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
 pub struct DefaultUniforms {
     pub resolution: [f32; 2],
+    pub mouse: [f32; 2],
+    pub mouse_click: [f32; 2],
+    pub mouse_drag_start: [f32; 2],
+    pub mouse_buttons: u32,
+    pub time: f32,
+    pub time_delta: f32,
+    pub frame: u32,
+    pub date: [f32; 4],
+    pub sample_rate: f32,
+    pub view_zoom: f32,
+    pub view_center: [f32; 2],
+    pub sample_index: u32,
+    _padding: [u32; 3],
 }
 impl DefaultUniforms {
-    pub fn new(width: f32, height: f32) -> Self {
+    pub fn new(
+        size: Size<u32>,
+        mouse: &Mouse,
+        clock: &Clock,
+        view: &View,
+        scale_factor: f32,
+    ) -> Self {
+        let physical = |point: Point| [point.x * scale_factor, point.y * scale_factor];
+        let (view_zoom, view_center) = view.uniforms(size);
+
+        Self {
+            resolution: [size.width as f32, size.height as f32],
+            mouse: physical(mouse.position),
+            mouse_click: physical(mouse.click),
+            mouse_drag_start: physical(mouse.drag_start),
+            mouse_buttons: mouse.buttons,
+            time: clock.time.as_secs_f32(),
+            time_delta: clock.time_delta.as_secs_f32(),
+            frame: clock.frame,
+            date: date(),
+            sample_rate: SAMPLE_RATE as f32,
+            view_zoom,
+            view_center,
+            sample_index: 0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Mirrors `struct Camera` in `shaders/camera.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct CameraUniforms {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+    pub position: [f32; 3],
+    pub fov: f32,
+}
+
+impl CameraUniforms {
+    pub fn new(camera: &Camera, size: Size<u32>) -> Self {
         Self {
-            resolution: [width, height],
+            view: camera.view(),
+            proj: camera.projection(size.width as f32 / size.height as f32),
+            position: camera.position(),
+            fov: camera.fov.to_radians(),
         }
     }
 }
 
-impl Default for DefaultUniforms {
-    fn default() -> Self {
-        Self::new(1000.0, 1000.0) // Default resolution
+/// The current year, month, day and seconds since midnight in UTC.
+fn date() -> [f32; 4] {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let days = (since_epoch.as_secs() / 86_400) as i64;
+    let seconds = since_epoch.as_secs_f64() - days as f64 * 86_400.0;
+
+    // Converts days since 1970-01-01 to a civil date, counting years from March so that the leap
+    // day comes last
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    [year as f32, month as f32, day as f32, seconds as f32]
+}
+
+/// The user defined uniforms as `struct Customs` declaration and matching buffer content.
+///
+/// The GLSL block declaration has the same layout, as std140 agrees with WGSL's uniform layout
+/// for the supported types.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CustomUniforms {
+    declaration: String,
+    glsl_declaration: String,
+    bytes: Vec<u8>,
+}
+
+impl CustomUniforms {
+    pub fn new(uniforms: &[Uniform]) -> Self {
+        if uniforms.is_empty() {
+            return Self::default();
+        }
+
+        let mut bytes = Vec::new();
+        for uniform in uniforms {
+            let (align, _) = uniform.value.layout();
+            bytes.resize(bytes.len().next_multiple_of(align), 0);
+            bytes.extend(uniform.value.to_bytes());
+        }
+        // Uniform buffer bindings have to be a multiple of 16 bytes
+        bytes.resize(bytes.len().next_multiple_of(16), 0);
+
+        let members: String = uniforms
+            .iter()
+            .map(|u| format!("    {},\n", u.to_shader_line()))
+            .collect();
+
+        let glsl_members: String = uniforms
+            .iter()
+            .map(|u| format!("    {} {};\n", u.value.to_glsl_type(), u.name))
+            .collect();
+
+        Self {
+            declaration: format!(
+                "struct Customs {{\n{members}}}\n\n@group(3) @binding(0) var<uniform> customs: Customs;\n"
+            ),
+            glsl_declaration: format!(
+                "layout(set = 3, binding = 0) uniform Customs {{\n{glsl_members}}} customs;\n"
+            ),
+            bytes,
+        }
+    }
+
+    pub fn declaration(&self) -> &str {
+        &self.declaration
+    }
+
+    pub fn glsl_declaration(&self) -> &str {
+        &self.glsl_declaration
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
     }
 }
 
-pub type CustomUniforms = [u8];
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::uniforms_editor::uniform::Type;
+
+    /// The member offsets and the size of the struct as naga lays it out.
+    fn layout(module: &naga::Module, name: &str) -> (Vec<(String, u32)>, u32) {
+        let (_, ty) = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some(name))
+            .unwrap();
+        match &ty.inner {
+            naga::TypeInner::Struct { members, span } => (
+                members
+                    .iter()
+                    .map(|member| (member.name.clone().unwrap(), member.offset))
+                    .collect(),
+                *span,
+            ),
+            _ => panic!("{name} is not a struct"),
+        }
+    }
+
+    fn wgsl_layout(source: &str, name: &str) -> (Vec<(String, u32)>, u32) {
+        layout(&naga::front::wgsl::parse_str(source).unwrap(), name)
+    }
+
+    fn glsl_layout(source: &str, name: &str) -> (Vec<(String, u32)>, u32) {
+        let module = naga::front::glsl::Frontend::default()
+            .parse(
+                &naga::front::glsl::Options::from(naga::ShaderStage::Fragment),
+                &format!("#version 450\n{source}\nvoid main() {{}}\n"),
+            )
+            .unwrap();
+        layout(&module, name)
+    }
+
+    fn uniform(name: &str, value: Type) -> Uniform {
+        Uniform {
+            value,
+            name: name.to_string(),
+        }
+    }
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn custom_uniforms_follow_uniform_buffer_layout() {
+        let customs = CustomUniforms::new(&[
+            uniform("a", Type::Float(1.0)),
+            uniform("b", Type::VecFloat3((2.0, 3.0, 4.0))),
+            uniform("c", Type::Float(5.0)),
+            uniform("d", Type::VecFloat2((6.0, 7.0))),
+            uniform("e", Type::VecFloat4((8.0, 9.0, 10.0, 11.0))),
+            uniform("f", Type::Int(-12)),
+        ]);
+        let bytes = customs.bytes();
+
+        // The vec3 aligns to 16, the following scalar fills its last 4 bytes and the size is
+        // rounded up to 16
+        assert_eq!(bytes.len(), 80);
+        assert_eq!(f32_at(bytes, 0), 1.0);
+        assert_eq!(&bytes[4..16], &[0; 12]);
+        assert_eq!(
+            [16, 20, 24].map(|offset| f32_at(bytes, offset)),
+            [2.0, 3.0, 4.0]
+        );
+        assert_eq!(f32_at(bytes, 28), 5.0);
+        assert_eq!([32, 36].map(|offset| f32_at(bytes, offset)), [6.0, 7.0]);
+        assert_eq!(&bytes[40..48], &[0; 8]);
+        assert_eq!(
+            [48, 52, 56, 60].map(|offset| f32_at(bytes, offset)),
+            [8.0, 9.0, 10.0, 11.0]
+        );
+        assert_eq!(i32::from_le_bytes(bytes[64..68].try_into().unwrap()), -12);
+        assert_eq!(&bytes[68..], &[0; 12]);
+
+        // Both declarations put the members where the bytes are
+        let offsets = [
+            ("a", 0),
+            ("b", 16),
+            ("c", 28),
+            ("d", 32),
+            ("e", 48),
+            ("f", 64),
+        ];
+        let expected: Vec<(String, u32)> = offsets
+            .iter()
+            .map(|(name, offset)| (name.to_string(), *offset))
+            .collect();
+        for (members, span) in [
+            wgsl_layout(customs.declaration(), "Customs"),
+            glsl_layout(customs.glsl_declaration(), "Customs"),
+        ] {
+            assert_eq!(members, expected);
+            assert_eq!(span as usize, bytes.len());
+        }
+    }
+
+    #[test]
+    fn custom_uniforms_are_empty_without_uniforms() {
+        let customs = CustomUniforms::new(&[]);
+
+        assert!(customs.is_empty());
+        assert!(customs.declaration().is_empty());
+        assert!(customs.glsl_declaration().is_empty());
+    }
+
+    #[test]
+    fn default_uniforms_match_the_shader_struct() {
+        let (members, span) = wgsl_layout(include_str!("shaders/uniforms.wgsl"), "Uniforms");
+
+        assert_eq!(std::mem::size_of::<DefaultUniforms>(), 96);
+        assert_eq!(span, 96);
+        let offset = |name: &str| {
+            members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, offset)| *offset as usize)
+        };
+        assert_eq!(
+            offset("date"),
+            Some(std::mem::offset_of!(DefaultUniforms, date))
+        );
+        assert_eq!(
+            offset("view_center"),
+            Some(std::mem::offset_of!(DefaultUniforms, view_center))
+        );
+        assert_eq!(
+            offset("sample_index"),
+            Some(std::mem::offset_of!(DefaultUniforms, sample_index))
+        );
+    }
+
+    #[test]
+    fn camera_uniforms_match_the_shader_struct() {
+        let (members, span) = wgsl_layout(include_str!("shaders/camera.wgsl"), "Camera");
+
+        assert_eq!(std::mem::size_of::<CameraUniforms>(), 144);
+        assert_eq!(span, 144);
+        assert_eq!(
+            members,
+            [
+                (String::from("view"), 0),
+                (String::from("proj"), 64),
+                (String::from("position"), 128),
+                (String::from("fov"), 140),
+            ]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use iced::{Point, Size, Vector};
+
+use crate::viewer::canvasscene::mouse::Mouse;
+
+/// Zoom factor per line scrolled with the mouse wheel.
+const ZOOM_STEP: f32 = 1.2;
+
+/// Scrolled pixels of touchpads counting as one line.
+pub const PIXELS_PER_LINE: f32 = 50.0;
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 512.0;
+
+/// Pan and zoom of the image shown by the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// Magnification of the image, at 1 it fills the viewer with one texel per physical pixel.
+    pub zoom: f32,
+    /// The point of the image at the center of the viewer, from 0 to 1 across the image.
+    pub center: Point,
+    /// Whether the shader renders the view itself from the `view_*` uniforms, instead of the
+    /// rendered image being magnified.
+    pub is_in_shader: bool,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            center: Point::new(0.5, 0.5),
+            is_in_shader: false,
+        }
+    }
+}
+
+impl View {
+    /// Shows the whole image, which is rendered at the physical size of the viewer and so also
+    /// shows one texel per physical pixel.
+    pub fn fit(self) -> Self {
+        Self {
+            is_in_shader: self.is_in_shader,
+            ..Self::default()
+        }
+    }
+
+    /// Zooms in by the lines scrolled, zooming out for negative ones, keeping the point of the
+    /// image under the cursor in place.
+    pub fn zoom_at(self, position: Point, bounds: Size, lines: f32) -> Self {
+        let zoom = (self.zoom * ZOOM_STEP.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+        let offset = relative(position, bounds) - Vector::new(0.5, 0.5);
+
+        Self {
+            zoom,
+            center: transform(self.center, self.zoom, position, bounds) - offset * (1.0 / zoom),
+            ..self
+        }
+    }
+
+    /// Drags the image along by logical pixels.
+    pub fn pan(self, delta: Vector, bounds: Size) -> Self {
+        Self {
+            center: self.center
+                - Vector::new(
+                    delta.x / (bounds.width * self.zoom),
+                    delta.y / (bounds.height * self.zoom),
+                ),
+            ..self
+        }
+    }
+
+    /// The point of the image under a position in the viewer, from 0 to 1 across the image.
+    pub fn image_point(&self, position: Point, bounds: Size) -> Point {
+        let (center, zoom) = self.displayed();
+        transform(center, zoom, position, bounds)
+    }
+
+    /// The mouse with its positions moved to the image under them, in logical pixels.
+    pub fn mouse(&self, mouse: &Mouse, bounds: Size) -> Mouse {
+        let image = |position: Point| {
+            let point = self.image_point(position, bounds);
+            Point::new(point.x * bounds.width, point.y * bounds.height)
+        };
+
+        Mouse {
+            position: image(mouse.position),
+            click: image(mouse.click),
+            drag_start: image(mouse.drag_start),
+            buttons: mouse.buttons,
+        }
+    }
+
+    /// The center and zoom of the blit to the viewer, mirrors `struct View` in
+    /// `shaders/texture.wgsl`.
+    pub fn blit(&self) -> [f32; 4] {
+        let (center, zoom) = self.displayed();
+        [center.x, center.y, zoom, 0.0]
+    }
+
+    /// The zoom and the center in pixels of an image of the given size passed to the shader.
+    pub fn uniforms(&self, size: Size<u32>) -> (f32, [f32; 2]) {
+        let (width, height) = (size.width as f32, size.height as f32);
+        if self.is_in_shader {
+            (self.zoom, [self.center.x * width, self.center.y * height])
+        } else {
+            (1.0, [0.5 * width, 0.5 * height])
+        }
+    }
+
+    /// The rendered image is shown as it is while the shader applies the view.
+    fn displayed(&self) -> (Point, f32) {
+        if self.is_in_shader {
+            (Point::new(0.5, 0.5), 1.0)
+        } else {
+            (self.center, self.zoom)
+        }
+    }
+}
+
+/// The point of the image at the position in the viewer when showing it at the center and zoom.
+fn transform(center: Point, zoom: f32, position: Point, bounds: Size) -> Point {
+    center + (relative(position, bounds) - Vector::new(0.5, 0.5)) * (1.0 / zoom)
+}
+
+fn relative(position: Point, bounds: Size) -> Vector {
+    Vector::new(position.x / bounds.width, position.y / bounds.height)
+}
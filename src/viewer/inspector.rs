@@ -0,0 +1,49 @@
+use iced::widget::{column, container, row, text};
+use iced::{Color, Element};
+
+use crate::viewer::canvasscene::Pixel;
+use crate::viewer::overlay;
+
+/// The pixel under the cursor, read back from the image pass.
+#[derive(Default)]
+pub struct Inspector {
+    pixel: Option<Pixel>,
+}
+
+impl Inspector {
+    /// Shows the latest pixel read back by the scene.
+    pub fn frame(&mut self, pixel: Option<Pixel>) {
+        self.pixel = pixel;
+    }
+
+    pub fn view<'a, Message: 'a>(&'a self) -> Element<'a, Message> {
+        let content: Element<_> = match &self.pixel {
+            Some(pixel) => {
+                let [r, g, b, a] = pixel.color;
+                let [x, y] = pixel.frag_coord();
+                let [u, v] = pixel.uv();
+                let [red, green, blue, alpha] = pixel.bytes;
+                let color = Color::from_rgba8(red, green, blue, f32::from(alpha) / 255.0);
+                let swatch = container(text(""))
+                    .width(16.0)
+                    .height(16.0)
+                    .style(move |_theme| container::Style {
+                        background: Some(color.into()),
+                        ..container::Style::default()
+                    });
+
+                column![
+                    row![swatch, text(pixel.hex())].spacing(5),
+                    text(format!("RGBA {r:.4} {g:.4} {b:.4} {a:.4}")),
+                    text(format!("fragCoord {x:.1} {y:.1}")),
+                    text(format!("UV {u:.4} {v:.4}")),
+                ]
+                .spacing(2)
+                .into()
+            }
+            None => text("Hover the scene to inspect a pixel").into(),
+        };
+
+        container(content).padding(5).style(overlay::style).into()
+    }
+}
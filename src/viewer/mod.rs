@@ -1,38 +1,347 @@
 mod canvasscene;
+mod inspector;
+mod overlay;
+pub mod transport;
 
-use canvasscene::CanvasScene;
+pub use canvasscene::camera;
+#[cfg(not(target_arch = "wasm32"))]
+pub use canvasscene::headless;
+pub use canvasscene::output;
+pub use canvasscene::portability;
+pub use canvasscene::prelude;
+pub use canvasscene::shadertoy;
+#[cfg(not(target_arch = "wasm32"))]
+pub use canvasscene::sound;
+use canvasscene::{Camera, CanvasScene, CompiledShader, Output, ShaderSource, View};
+use inspector::Inspector;
+use output::{Encoding, Precision, ToneMapping};
+use overlay::Overlay;
+use transport::Transport;
 
-use iced::widget::shader;
-use iced::{Element, Fill, Task};
+use iced::widget::{
+    button, column, container, pick_list, row, shader, slider, stack, text, toggler,
+};
+use iced::{window, Center, Element, Fill, Subscription, Task};
 
-use crate::shader_update::ShaderUpdate;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::std::Instant;
+
+use crate::diagnostics::Diagnostic;
+use crate::shader_update::{Pass, ShaderUpdate};
+use crate::util;
+
+/// Time without further edits before a changed shader gets compiled.
+const DEBOUNCE: Duration = Duration::from_millis(250);
 
 pub struct Viewer {
     scene: CanvasScene,
+    diagnostics: Vec<Diagnostic>,
+    generation: usize,
+    /// The generation of the latest source of every pass, older compilation results are dropped.
+    latest: BTreeMap<Pass, usize>,
+    pending: BTreeMap<Pass, ShaderSource>,
+    /// Shown on top of the scene if set.
+    overlay: Option<Overlay>,
+    /// Shows the pixel under the cursor if set.
+    inspector: Option<Inspector>,
+    transport: Transport,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     UpdatePipeline(ShaderUpdate),
+    Compile(usize),
+    Compiled(usize, Pass, Result<CompiledShader, Vec<Diagnostic>>),
+    Transport(transport::Message),
+    ViewChanged(View),
+    OutputChanged(Output),
+    CameraChanged(Camera),
+    ToggleOverlay,
+    ToggleInspector,
+    Frame(Instant),
 }
 
 impl Viewer {
     pub fn new(shader: String) -> Self {
+        let (scene, diagnostics) = CanvasScene::new(shader);
         Self {
-            scene: CanvasScene::new(shader),
+            scene,
+            diagnostics,
+            generation: 0,
+            latest: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            overlay: None,
+            inspector: None,
+            transport: Transport::default(),
         }
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
+            Message::UpdatePipeline(ShaderUpdate::Transport(transport)) => {
+                self.transport = transport;
+                self.update_clock();
+                Task::none()
+            }
             Message::UpdatePipeline(message) => {
-                self.scene.update(message);
+                if let ShaderUpdate::RemovePass(pass) = message {
+                    self.latest.remove(&pass);
+                    self.pending.remove(&pass);
+                    self.diagnostics
+                        .retain(|diagnostic| diagnostic.pass != pass);
+                }
+
+                let sources = self.scene.update(message);
+                if sources.is_empty() {
+                    return Task::none();
+                }
+
+                self.generation += 1;
+                for source in sources {
+                    self.latest.insert(source.pass(), self.generation);
+                    self.pending.insert(source.pass(), source);
+                }
+
+                let generation = self.generation;
+                Task::perform(util::sleep(DEBOUNCE), move |()| {
+                    Message::Compile(generation)
+                })
+            }
+            Message::Compile(generation) => {
+                if generation != self.generation {
+                    return Task::none();
+                }
+
+                Task::batch(
+                    std::mem::take(&mut self.pending)
+                        .into_values()
+                        .map(|source| {
+                            let pass = source.pass();
+                            Task::perform(compile(source), move |result| {
+                                Message::Compiled(generation, pass, result)
+                            })
+                        }),
+                )
+            }
+            Message::Compiled(generation, pass, result) => {
+                if self.latest.get(&pass) != Some(&generation) {
+                    return Task::none();
+                }
+
+                self.diagnostics
+                    .retain(|diagnostic| diagnostic.pass != pass);
+                match result {
+                    Ok(compiled) => self.scene.activate(compiled),
+                    Err(diagnostics) => self.diagnostics.extend(diagnostics),
+                }
+                Task::none()
+            }
+            Message::Transport(message) => {
+                let task = self.transport.update(message).map(Message::Transport);
+                self.update_clock();
+                task
+            }
+            Message::ViewChanged(view) => {
+                self.scene.set_view(view);
+                Task::none()
+            }
+            Message::OutputChanged(output) => {
+                self.scene.set_output(output);
+                Task::none()
+            }
+            Message::CameraChanged(camera) => {
+                self.scene.set_camera(camera);
+                Task::none()
+            }
+            Message::ToggleOverlay => {
+                self.overlay = match self.overlay {
+                    Some(_) => None,
+                    None => Some(Overlay::default()),
+                };
+                Task::none()
+            }
+            Message::ToggleInspector => {
+                self.inspector = match self.inspector {
+                    Some(_) => None,
+                    None => Some(Inspector::default()),
+                };
+                self.scene.set_inspecting(self.inspector.is_some());
+                Task::none()
+            }
+            Message::Frame(now) => {
+                self.transport.frame(now);
+                self.update_clock();
+                if let Some(overlay) = &mut self.overlay {
+                    overlay.frame(now, self.scene.timings());
+                }
+                if let Some(inspector) = &mut self.inspector {
+                    inspector.frame(self.scene.pixel());
+                }
                 Task::none()
             }
         }
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        shader(&self.scene).width(Fill).height(Fill).into()
+        let view = self.scene.view();
+        let camera = self.scene.camera();
+        let controls = row![
+            self.transport.view().map(Message::Transport),
+            text(format!("{:.0}%", view.zoom * 100.0)),
+            button("Fit").on_press(Message::ViewChanged(view.fit())),
+            toggler(view.is_in_shader)
+                .label("Zoom in shader")
+                .on_toggle(move |is_in_shader| Message::ViewChanged(View {
+                    is_in_shader,
+                    ..view
+                })),
+            pick_list(camera::Mode::ALL, Some(camera.mode), move |mode| {
+                Message::CameraChanged(Camera { mode, ..camera })
+            }),
+            button("Reset camera").on_press(Message::CameraChanged(camera.reset())),
+            button("Stats").on_press(Message::ToggleOverlay),
+            button("Inspect").on_press(Message::ToggleInspector),
+        ]
+        .spacing(5);
+
+        let output = self.scene.output();
+        let mut output_controls = row![
+            pick_list(Precision::ALL, Some(output.precision), move |precision| {
+                Message::OutputChanged(Output {
+                    precision,
+                    ..output
+                })
+            }),
+            pick_list(Encoding::ALL, Some(output.encoding), move |encoding| {
+                Message::OutputChanged(Output { encoding, ..output })
+            }),
+            pick_list(
+                ToneMapping::ALL,
+                Some(output.tone_mapping),
+                move |tone_mapping| Message::OutputChanged(Output {
+                    tone_mapping,
+                    ..output
+                })
+            ),
+            text(format!("Exposure {:+.1} EV", output.exposure)),
+            slider(-8.0..=8.0, output.exposure, move |exposure| {
+                Message::OutputChanged(Output { exposure, ..output })
+            })
+            .step(0.1)
+            .width(160),
+            toggler(output.is_accumulating)
+                .label("Accumulate")
+                .on_toggle(move |is_accumulating| Message::OutputChanged(Output {
+                    is_accumulating,
+                    ..output
+                })),
+        ]
+        .spacing(5)
+        .align_y(Center);
+        if output.is_accumulating {
+            output_controls =
+                output_controls.push(text(format!("{} samples", self.scene.samples())));
+        }
+
+        let mut layers: Vec<Element<_>> = vec![shader(&self.scene).width(Fill).height(Fill).into()];
+        if let Some(overlay) = &self.overlay {
+            layers.push(container(overlay.view()).padding(10).into());
+        }
+        if let Some(inspector) = &self.inspector {
+            layers.push(
+                container(inspector.view())
+                    .padding(10)
+                    .align_bottom(Fill)
+                    .into(),
+            );
+        }
+
+        column![controls, output_controls, stack(layers)].into()
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        // The inspector polls for pixels read back after the scene was last drawn and the sample
+        // count grows while accumulating
+        if self.transport.is_playing()
+            || self.overlay.is_some()
+            || self.inspector.is_some()
+            || self.scene.output().is_accumulating
+        {
+            window::frames().map(Message::Frame)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    pub fn title(&self) -> String {
+        if self.scene.is_stale() {
+            String::from("Viewer • showing last valid shader")
+        } else {
+            String::from("Viewer")
+        }
+    }
+
+    /// How the image pass is shown, to be saved with the project.
+    pub fn output(&self) -> Output {
+        self.scene.output()
+    }
+
+    /// The camera, to be saved with the project.
+    pub fn camera(&self) -> Camera {
+        self.scene.camera()
+    }
+
+    /// The timeline, to be saved with the project.
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Checks the latest shaders of all passes against the targets of [`portability`], on a
+    /// blocking thread on native. On the web it checks on the main thread.
+    pub fn check_portability(
+        &self,
+    ) -> impl Future<Output = Result<Vec<portability::Report>, Vec<Diagnostic>>> {
+        let sources = self.scene.sources();
+        async move {
+            util::spawn_blocking(move || portability::check(sources))
+                .await
+                .unwrap_or_else(|error| Err(vec![failure(Pass::Image, error)]))
+        }
+    }
+
+    /// Shows the scene at the time of the transport.
+    fn update_clock(&mut self) {
+        self.scene
+            .set_clock(self.transport.clock(), self.transport.is_playing());
+    }
+}
+
+/// Compiles the shader on a blocking thread on native, on the web it compiles synchronously.
+///
+/// The web only yields to the event loop before compiling, a long compilation still freezes the
+/// page.
+async fn compile(source: ShaderSource) -> Result<CompiledShader, Vec<Diagnostic>> {
+    let pass = source.pass();
+    util::spawn_blocking(move || source.compile())
+        .await
+        .unwrap_or_else(|error| Err(vec![failure(pass, error)]))
+}
+
+/// Reports blocking work that failed without a result, like a panicking compiler.
+fn failure(pass: Pass, error: util::Error) -> Diagnostic {
+    Diagnostic {
+        pass,
+        message: error.to_string(),
+        location: None,
     }
 }
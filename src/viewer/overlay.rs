@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::std::Instant;
+
+use iced::widget::canvas::{self, Canvas, Path, Stroke};
+use iced::widget::{column, container, text};
+use iced::{mouse, Color, Element, Point, Rectangle, Renderer, Theme};
+
+use crate::viewer::canvasscene::Timings;
+
+/// Number of frames shown in the graph.
+const HISTORY: usize = 120;
+
+/// Frame time at the top of the graph, unless a frame took longer.
+const GRAPH_MIN_SCALE: Duration = Duration::from_micros(33_333);
+
+const GPU_COLOR: Color = Color::from_rgb(0.4, 0.8, 1.0);
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    frame: Duration,
+    gpu: Option<Duration>,
+}
+
+/// Frame rate and timings of the viewer with a graph of the last frames.
+#[derive(Default)]
+pub struct Overlay {
+    last_frame: Option<Instant>,
+    samples: VecDeque<Sample>,
+    timings: Timings,
+}
+
+impl Overlay {
+    /// Records a frame drawn at `now` with the timings measured so far.
+    pub fn frame(&mut self, now: Instant, timings: Timings) {
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            if self.samples.len() == HISTORY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(Sample {
+                frame: now - last_frame,
+                gpu: timings.gpu,
+            });
+        }
+        self.timings = timings;
+    }
+
+    pub fn view<'a, Message: 'a>(&'a self) -> Element<'a, Message> {
+        let frame_time = (!self.samples.is_empty()).then(|| {
+            self.samples
+                .iter()
+                .map(|sample| sample.frame)
+                .sum::<Duration>()
+                / self.samples.len() as u32
+        });
+        let fps = match frame_time {
+            Some(frame_time) if !frame_time.is_zero() => format!(
+                "{:.0} FPS, {:.2} ms",
+                1.0 / frame_time.as_secs_f64(),
+                milliseconds(frame_time)
+            ),
+            _ => String::from("– FPS"),
+        };
+        let cpu = match self.timings.cpu {
+            Some(cpu) => format!("CPU {:.2} ms", milliseconds(cpu)),
+            None => String::from("CPU –"),
+        };
+        let gpu = match (self.timings.has_timestamps, self.timings.gpu) {
+            (false, _) => String::from("GPU not measured, the device has no timestamp queries"),
+            (true, None) => String::from("GPU –"),
+            (true, Some(gpu)) => format!("GPU {:.2} ms", milliseconds(gpu)),
+        };
+
+        container(
+            column![
+                text(fps),
+                text(cpu),
+                text(gpu).color(GPU_COLOR),
+                Canvas::new(self).width(HISTORY as f32 * 2.0).height(60.0),
+            ]
+            .spacing(2),
+        )
+        .padding(5)
+        .style(style)
+        .into()
+    }
+}
+
+/// Translucent dark panel for readouts on top of the scene.
+pub fn style(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(
+            Color {
+                a: 0.6,
+                ..Color::BLACK
+            }
+            .into(),
+        ),
+        text_color: Some(Color::WHITE),
+        ..container::Style::default()
+    }
+}
+
+impl<Message> canvas::Program<Message> for Overlay {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+
+        let scale = self
+            .samples
+            .iter()
+            .map(|sample| sample.frame)
+            .max()
+            .unwrap_or_default()
+            .max(GRAPH_MIN_SCALE);
+        let step = bounds.width / (HISTORY - 1) as f32;
+        let point = |index: usize, duration: Duration| {
+            let height = duration.as_secs_f32() / scale.as_secs_f32();
+            Point::new(index as f32 * step, bounds.height * (1.0 - height))
+        };
+
+        // 60 FPS for reference
+        let target = point(0, Duration::from_micros(16_667)).y;
+        frame.stroke(
+            &Path::line(Point::new(0.0, target), Point::new(bounds.width, target)),
+            Stroke::default().with_color(Color::from_rgba(1.0, 1.0, 1.0, 0.3)),
+        );
+
+        let graph = |value: fn(&Sample) -> Option<Duration>| {
+            Path::new(|builder| {
+                let mut is_drawing = false;
+                for (index, sample) in self.samples.iter().enumerate() {
+                    let duration = value(sample);
+                    match duration {
+                        Some(duration) if is_drawing => builder.line_to(point(index, duration)),
+                        Some(duration) => builder.move_to(point(index, duration)),
+                        None => {}
+                    }
+                    is_drawing = duration.is_some();
+                }
+            })
+        };
+        frame.stroke(
+            &graph(|sample| Some(sample.frame)),
+            Stroke::default().with_color(Color::WHITE),
+        );
+        frame.stroke(
+            &graph(|sample| sample.gpu),
+            Stroke::default().with_color(GPU_COLOR),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::std::Instant;
+
+use iced::keyboard::{self, key};
+use iced::widget::{
+    button, column, container, horizontal_space, pick_list, row, slider, text, toggler, tooltip,
+};
+use iced::{Center, Element, Task};
+use serde::{Deserialize, Serialize};
+
+use crate::viewer::canvasscene::Clock;
+
+/// Playback speeds to pick from, negative speeds play in reverse.
+const SPEEDS: [f32; 8] = [-2.0, -1.0, -0.5, 0.25, 0.5, 1.0, 2.0, 4.0];
+
+/// Frame rates to pick from for stepping single frames.
+const FRAME_RATES: [u32; 6] = [24, 25, 30, 50, 60, 120];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    TogglePlayback,
+    /// Jumps to the start of the timeline, or of the loop while looping.
+    Restart,
+    StepForward,
+    StepBackward,
+    Seek(f32),
+    SpeedSelected(f32),
+    FrameRateSelected(u32),
+    /// Sets the start of the loop to the current time.
+    SetLoopIn,
+    /// Sets the end of the loop to the current time.
+    SetLoopOut,
+    LoopToggled(bool),
+}
+
+/// The timeline of the viewer, saved with the project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transport {
+    time: Duration,
+    is_playing: bool,
+    /// Multiplies the time passing between frames.
+    speed: f32,
+    /// The frame rate of single steps.
+    frame_rate: u32,
+    loop_in: Duration,
+    /// The end of the loop and of the scrub slider, unless the time went past it.
+    loop_out: Duration,
+    is_looping: bool,
+    #[serde(skip)]
+    time_delta: Duration,
+    #[serde(skip)]
+    frame: u32,
+    /// When the last frame was shown during playback, the time advances by the time since.
+    #[serde(skip)]
+    last_frame: Option<Instant>,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            time: Duration::ZERO,
+            is_playing: true,
+            speed: 1.0,
+            frame_rate: 60,
+            loop_in: Duration::ZERO,
+            loop_out: Duration::from_secs(10),
+            is_looping: false,
+            time_delta: Duration::ZERO,
+            frame: 0,
+            last_frame: None,
+        }
+    }
+}
+
+impl Transport {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::TogglePlayback => {
+                self.is_playing = !self.is_playing;
+                self.last_frame = None;
+            }
+            Message::Restart => {
+                self.time = if self.is_looping {
+                    self.loop_in
+                } else {
+                    Duration::ZERO
+                };
+                self.frame = 0;
+                self.last_frame = None;
+            }
+            Message::StepForward => self.step(true),
+            Message::StepBackward => self.step(false),
+            Message::Seek(seconds) => {
+                self.time = Duration::from_secs_f32(seconds.max(0.0));
+                self.frame = (seconds.max(0.0) * self.frame_rate as f32) as u32;
+            }
+            Message::SpeedSelected(speed) => self.speed = speed,
+            Message::FrameRateSelected(frame_rate) => self.frame_rate = frame_rate,
+            Message::SetLoopIn => {
+                self.loop_in = self.time;
+                self.loop_out = self.loop_out.max(self.time);
+            }
+            Message::SetLoopOut => {
+                self.loop_out = self.time;
+                self.loop_in = self.loop_in.min(self.time);
+            }
+            Message::LoopToggled(is_looping) => self.is_looping = is_looping,
+        }
+
+        Task::none()
+    }
+
+    /// Advances the time to a frame shown at `now` during playback.
+    pub fn frame(&mut self, now: Instant) {
+        if !self.is_playing {
+            return;
+        }
+
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            self.time_delta = now - last_frame;
+            self.frame = self.frame.wrapping_add(1);
+            self.move_by(self.time_delta.as_secs_f64() * f64::from(self.speed));
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let controls = row![
+            control("Restart", "Restart (Home)", Message::Restart),
+            control("<", "Previous frame (Left)", Message::StepBackward),
+            control(
+                if self.is_playing { "Pause" } else { "Play" },
+                "Play or pause (Space)",
+                Message::TogglePlayback
+            ),
+            control(">", "Next frame (Right)", Message::StepForward),
+            text(format!(
+                "{:.2} s, frame {}",
+                self.time.as_secs_f32(),
+                self.frame
+            )),
+            horizontal_space(),
+            text("Speed"),
+            pick_list(SPEEDS, Some(self.speed), Message::SpeedSelected),
+            text("Step fps"),
+            pick_list(
+                FRAME_RATES,
+                Some(self.frame_rate),
+                Message::FrameRateSelected
+            ),
+        ]
+        .spacing(5)
+        .align_y(Center);
+
+        let end = self
+            .loop_out
+            .max(self.time)
+            .max(Duration::from_secs(1))
+            .as_secs_f32();
+        let timeline = row![
+            slider(0.0..=end, self.time.as_secs_f32(), Message::Seek).step(0.01),
+            control("In", "Start the loop here ([)", Message::SetLoopIn),
+            control("Out", "End the loop here (])", Message::SetLoopOut),
+            toggler(self.is_looping)
+                .label(format!(
+                    "Loop {:.2} s to {:.2} s",
+                    self.loop_in.as_secs_f32(),
+                    self.loop_out.as_secs_f32()
+                ))
+                .on_toggle(Message::LoopToggled),
+        ]
+        .spacing(5)
+        .align_y(Center);
+
+        column![controls, timeline].spacing(5).into()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    /// The time, frame and frame time the scene is rendered at.
+    pub fn clock(&self) -> Clock {
+        Clock {
+            time: self.time,
+            time_delta: self.time_delta,
+            frame: self.frame,
+        }
+    }
+
+    /// Pauses and moves one frame at the step frame rate.
+    fn step(&mut self, is_forward: bool) {
+        self.is_playing = false;
+        self.last_frame = None;
+        self.time_delta = Duration::from_secs_f64(1.0 / f64::from(self.frame_rate));
+
+        let seconds = self.time_delta.as_secs_f64();
+        if is_forward {
+            self.frame = self.frame.wrapping_add(1);
+            self.move_by(seconds);
+        } else {
+            self.frame = self.frame.saturating_sub(1);
+            self.move_by(-seconds);
+        }
+    }
+
+    /// Moves the time, wrapping around within the loop while looping and stopping at zero.
+    fn move_by(&mut self, seconds: f64) {
+        let mut time = self.time.as_secs_f64() + seconds;
+
+        let (loop_in, loop_out) = (self.loop_in.as_secs_f64(), self.loop_out.as_secs_f64());
+        if self.is_looping && loop_out > loop_in {
+            time = loop_in + (time - loop_in).rem_euclid(loop_out - loop_in);
+        }
+
+        self.time = Duration::from_secs_f64(time.max(0.0));
+    }
+}
+
+/// The transport message bound to a key pressed outside of text inputs.
+pub fn shortcut(key: &keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    if !modifiers.is_empty() {
+        return None;
+    }
+
+    match key.as_ref() {
+        keyboard::Key::Named(key::Named::Space) => Some(Message::TogglePlayback),
+        keyboard::Key::Named(key::Named::ArrowLeft) => Some(Message::StepBackward),
+        keyboard::Key::Named(key::Named::ArrowRight) => Some(Message::StepForward),
+        keyboard::Key::Named(key::Named::Home) => Some(Message::Restart),
+        keyboard::Key::Character("[") => Some(Message::SetLoopIn),
+        keyboard::Key::Character("]") => Some(Message::SetLoopOut),
+        _ => None,
+    }
+}
+
+fn control<'a>(label: &'a str, description: &'a str, message: Message) -> Element<'a, Message> {
+    tooltip(
+        button(label).on_press(message),
+        description,
+        tooltip::Position::FollowCursor,
+    )
+    .style(container::rounded_box)
+    .into()
+}